@@ -0,0 +1,38 @@
+//! Sustained write-path throughput, without audio hardware: synthetic
+//! buffers are pushed through `write_input_data` exactly as the cpal
+//! callback would, over both the mutex path and the lock-free ring. The
+//! drop counts printed after each run are what the ring redesign is
+//! meant to shrink at high rates.
+
+use audio_recorder::recorder::bench::WriteHarness;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Rate/channel pairs bracketing the deployments we care about, up to
+/// the 192 kHz four-channel arrays that show drops in the field.
+const CONFIGS: &[(u32, u16)] = &[(48_000, 2), (192_000, 4)];
+
+fn write_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write_input_data");
+    for &(sample_rate, channels) in CONFIGS {
+        // One 10 ms callback buffer, the typical cpal delivery size.
+        let len = (sample_rate / 100) as usize * channels as usize;
+        let buffer: Vec<f32> = (0..len).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+        group.throughput(Throughput::Elements(len as u64));
+        for (label, lock_free) in [("mutex", false), ("ring", true)] {
+            let id = BenchmarkId::new(label, format!("{}Hz/{}ch", sample_rate, channels));
+            group.bench_with_input(id, &buffer, |b, buffer| {
+                let harness = WriteHarness::new(sample_rate, channels, lock_free);
+                b.iter(|| harness.feed(buffer));
+                let (written, dropped) = harness.finish();
+                eprintln!(
+                    "{} {} Hz {} ch: {} samples written, {} dropped",
+                    label, sample_rate, channels, written, dropped
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, write_path);
+criterion_main!(benches);
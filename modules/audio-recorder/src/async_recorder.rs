@@ -0,0 +1,124 @@
+//! Async control surface over a recorder running on a dedicated capture
+//! thread. The cpal stream and wav writer never leave that thread — a
+//! stream is not `Send` — so only completion and cancellation cross into
+//! the async world. The future is executor-agnostic and works under tokio
+//! or any other runtime without blocking it.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use anyhow::Error;
+
+use crate::interrupt::StopHandle;
+use crate::recorder::Recorder;
+
+/// A recording running on its own capture thread. Awaiting it resolves to
+/// the path of the last finalized file once the recording completes.
+pub struct AsyncRecorder {
+    shared: Arc<Mutex<Shared>>,
+}
+
+struct Shared {
+    result: Option<Result<Option<PathBuf>, Error>>,
+    waker: Option<Waker>,
+    stop: Option<StopHandle>,
+    cancelled: bool,
+}
+
+impl AsyncRecorder {
+    /// Spawns a capture thread that builds a recorder with `setup` and
+    /// records for `secs` seconds. The recorder is constructed on the
+    /// capture thread because it cannot be sent across threads.
+    pub fn record_secs<F>(setup: F, secs: u64) -> Self
+    where
+        F: FnOnce() -> Result<Recorder, Error> + Send + 'static,
+    {
+        Self::spawn(setup, move |rec| Ok(rec.record_secs(secs)?))
+    }
+
+    /// Spawns a capture thread recording a single file until cancelled or
+    /// interrupted by a signal.
+    pub fn record<F>(setup: F) -> Self
+    where
+        F: FnOnce() -> Result<Recorder, Error> + Send + 'static,
+    {
+        Self::spawn(setup, |rec| Ok(rec.record()?))
+    }
+
+    fn spawn<F, R>(setup: F, run: R) -> Self
+    where
+        F: FnOnce() -> Result<Recorder, Error> + Send + 'static,
+        R: FnOnce(&mut Recorder) -> Result<(), Error> + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            waker: None,
+            stop: None,
+            cancelled: false,
+        }));
+        let thread_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            let result = record_on_thread(setup, run, &thread_shared);
+            let mut shared = thread_shared.lock().unwrap();
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { shared }
+    }
+
+    /// Stops the recording the same way a console interrupt would,
+    /// finalizing the file in progress. The future then resolves normally.
+    /// Safe to call before the capture thread has finished initializing.
+    pub fn cancel(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.cancelled = true;
+        if let Some(stop) = &shared.stop {
+            stop.stop();
+        }
+    }
+}
+
+impl Future for AsyncRecorder {
+    type Output = Result<Option<PathBuf>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Builds and runs the recorder, publishing its stop handle so `cancel`
+/// works, and honoring a cancellation that raced ahead of initialization.
+fn record_on_thread<F, R>(
+    setup: F,
+    run: R,
+    shared: &Mutex<Shared>,
+) -> Result<Option<PathBuf>, Error>
+where
+    F: FnOnce() -> Result<Recorder, Error>,
+    R: FnOnce(&mut Recorder) -> Result<(), Error>,
+{
+    let mut rec = setup()?;
+    let stop = rec.stop_handle();
+    {
+        let mut shared = shared.lock().unwrap();
+        if shared.cancelled {
+            stop.stop();
+        }
+        shared.stop = Some(stop);
+    }
+    run(&mut rec)?;
+    Ok(rec.current_file())
+}
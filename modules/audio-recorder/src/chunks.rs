@@ -0,0 +1,135 @@
+//! Helpers for appending RIFF chunks that hound cannot write itself, such
+//! as Broadcast Wave metadata. Chunks are appended after the data chunk of
+//! a finalized file and the RIFF size field is patched to match.
+
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+
+/// Appends a chunk to a finalized wav file and patches the RIFF size.
+pub fn append_chunk(path: &Path, id: [u8; 4], data: &[u8]) -> Result<(), Error> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut riff = [0u8; 4];
+    file.read_exact(&mut riff)?;
+    if &riff != b"RIFF" {
+        return Err(anyhow!("{} is not a RIFF file", path.display()));
+    }
+    file.seek(SeekFrom::End(0))?;
+    file.write_all(&id)?;
+    file.write_all(&(data.len() as u32).to_le_bytes())?;
+    file.write_all(data)?;
+    // Chunks must be word-aligned; odd-sized payloads get a pad byte.
+    if data.len() % 2 == 1 {
+        file.write_all(&[0])?;
+    }
+    let riff_size = file.seek(SeekFrom::End(0))? - 8;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(riff_size as u32).to_le_bytes())?;
+    Ok(())
+}
+
+/// Builds a version 1 Broadcast Wave `bext` chunk payload carrying the
+/// recording's description, originator, and origination date and time.
+pub fn bext_chunk(description: &str, originator: &str, date: &str, time: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(602);
+    push_fixed(&mut data, description, 256);
+    push_fixed(&mut data, originator, 32);
+    push_fixed(&mut data, "", 32); // OriginatorReference
+    push_fixed(&mut data, date, 10); // yyyy-mm-dd
+    push_fixed(&mut data, time, 8); // hh:mm:ss
+    data.extend_from_slice(&0u32.to_le_bytes()); // TimeReferenceLow
+    data.extend_from_slice(&0u32.to_le_bytes()); // TimeReferenceHigh
+    data.extend_from_slice(&1u16.to_le_bytes()); // Version
+    data.extend_from_slice(&[0u8; 64]); // UMID
+    data.extend_from_slice(&[0u8; 190]); // Reserved
+    data
+}
+
+/// Builds an `iXML` chunk payload embedding the deployment location so a
+/// recording carries its position without a separate notebook entry.
+pub fn ixml_location_chunk(lat: f64, lon: f64, depth_m: Option<f32>) -> Vec<u8> {
+    let depth = depth_m
+        .map(|d| format!("    <DEPTH_M>{}</DEPTH_M>\n", d))
+        .unwrap_or_default();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <BWFXML>\n\
+         \x20 <IXML_VERSION>1.61</IXML_VERSION>\n\
+         \x20 <LOCATION>\n\
+         \x20   <LATITUDE>{}</LATITUDE>\n\
+         \x20   <LONGITUDE>{}</LONGITUDE>\n{}\
+         \x20 </LOCATION>\n\
+         </BWFXML>\n",
+        lat, lon, depth
+    )
+    .into_bytes()
+}
+
+/// Builds a `cue ` chunk payload from marker sample offsets (in frames
+/// into the data chunk). Cue IDs are 1-based indices, matching the labels
+/// written by [`adtl_chunk`].
+pub fn cue_chunk(offsets: &[u64]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + offsets.len() * 24);
+    data.extend_from_slice(&(offsets.len() as u32).to_le_bytes());
+    for (index, &offset) in offsets.iter().enumerate() {
+        let offset = offset.min(u32::MAX as u64) as u32;
+        data.extend_from_slice(&(index as u32 + 1).to_le_bytes()); // dwName
+        data.extend_from_slice(&offset.to_le_bytes()); // dwPosition
+        data.extend_from_slice(b"data"); // fccChunk
+        data.extend_from_slice(&0u32.to_le_bytes()); // dwChunkStart
+        data.extend_from_slice(&0u32.to_le_bytes()); // dwBlockStart
+        data.extend_from_slice(&offset.to_le_bytes()); // dwSampleOffset
+    }
+    data
+}
+
+/// Builds a `LIST` chunk payload of type `adtl` holding one `labl` entry
+/// per marker, so DAWs show the label text next to each cue point.
+pub fn adtl_chunk(labels: &[String]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(b"adtl");
+    for (index, label) in labels.iter().enumerate() {
+        let mut text = label.as_bytes().to_vec();
+        text.push(0); // labels are nul-terminated
+        data.extend_from_slice(b"labl");
+        data.extend_from_slice(&(4 + text.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(index as u32 + 1).to_le_bytes()); // dwName
+        data.extend_from_slice(&text);
+        // Sub-chunks are word-aligned like top-level chunks.
+        if text.len() % 2 == 1 {
+            data.push(0);
+        }
+    }
+    data
+}
+
+/// Builds a `LIST` chunk payload of type `INFO` holding one sub-chunk per
+/// tag, e.g. `IART` for the operator or `ICMT` for a comment, so
+/// cataloging tools that read standard WAV INFO tags can index the file.
+pub fn info_chunk(tags: &[([u8; 4], String)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(b"INFO");
+    for (id, value) in tags {
+        let mut text = value.as_bytes().to_vec();
+        text.push(0); // INFO values are nul-terminated
+        data.extend_from_slice(id);
+        data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        data.extend_from_slice(&text);
+        // Sub-chunks are word-aligned like top-level chunks.
+        if text.len() % 2 == 1 {
+            data.push(0);
+        }
+    }
+    data
+}
+
+/// Writes `value` as a fixed-length ASCII field, truncated or padded with
+/// zero bytes to exactly `len` bytes.
+fn push_fixed(data: &mut Vec<u8>, value: &str, len: usize) {
+    let bytes = value.as_bytes();
+    let take = bytes.len().min(len);
+    data.extend_from_slice(&bytes[..take]);
+    data.resize(data.len() + (len - take), 0);
+}
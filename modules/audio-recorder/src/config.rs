@@ -0,0 +1,61 @@
+//! Recorder configuration loaded from a TOML file, so deployments can be
+//! reproduced by copying a file instead of retyping command-line flags.
+//! Missing keys fall back to the same defaults the command line uses;
+//! unknown keys are rejected so a typo cannot silently deploy the wrong
+//! settings.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecorderConfig {
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: u32,
+    #[serde(default = "default_channels")]
+    pub channels: u16,
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: u32,
+    /// Input device name as shown by `--list-devices`; the host default
+    /// when omitted.
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+impl RecorderConfig {
+    /// Parses a TOML config file, reporting the file name and the parser's
+    /// explanation (including unknown keys) on failure.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| anyhow!("cannot read config file {}: {}", path.display(), err))?;
+        toml::from_str(&text)
+            .map_err(|err| anyhow!("invalid config file {}: {}", path.display(), err))
+    }
+}
+
+fn default_name() -> String {
+    "audio".to_string()
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("/output/audio")
+}
+
+fn default_sample_rate() -> u32 {
+    44100
+}
+
+fn default_channels() -> u16 {
+    2
+}
+
+fn default_buffer_size() -> u32 {
+    1024
+}
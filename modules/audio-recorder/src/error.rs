@@ -0,0 +1,50 @@
+//! Structured error type for the public entry points, so embedders can
+//! match on failure kinds instead of scraping `anyhow` message strings.
+//! Internals keep using `anyhow`; the conversion below recovers the
+//! structured variants at the API boundary by downcasting, so a
+//! `DeviceNotFound` raised deep in the getters still surfaces as that
+//! variant.
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RecorderError {
+    #[error("requested host {0} not available")]
+    HostUnavailable(String),
+    #[error("input device '{name}' not found, available devices: {available:?}")]
+    DeviceNotFound {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("no input device available on host {0}")]
+    NoDefaultDevice(String),
+    #[error("unsupported configuration: {0}")]
+    UnsupportedConfig(String),
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("wav error: {0}")]
+    Wav(#[from] hound::Error),
+    #[error("device acquisition timed out after {0} s")]
+    Timeout(u64),
+    #[error("recording interrupted")]
+    Interrupted,
+    #[error("writer lock poisoned by a panicked audio thread")]
+    Poisoned,
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<anyhow::Error> for RecorderError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<RecorderError>() {
+            Ok(err) => err,
+            Err(err) => match err.downcast::<std::io::Error>() {
+                Ok(err) => Self::Io(err),
+                Err(err) => match err.downcast::<hound::Error>() {
+                    Ok(err) => Self::Wav(err),
+                    Err(err) => Self::Other(format!("{:#}", err)),
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! FLAC encoding worker. The audio callback hands processed f32 buffers
+//! over a bounded channel and a dedicated thread encodes them block by
+//! block, so the compression cost can never stall capture and cause
+//! underruns. Frames are appended to the output file as they are encoded;
+//! at the end the STREAMINFO header is rewritten in place with the final
+//! block and sample counts, so memory use stays flat over long
+//! recordings.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{anyhow, Error};
+use flacenc::bitsink::ByteSink;
+use flacenc::component::{BitRepr, StreamInfo};
+use flacenc::encode_fixed_size_frame;
+use flacenc::config::Encoder;
+use flacenc::error::Verify;
+use flacenc::source::{Fill, FrameBuf};
+use hound::WavSpec;
+
+/// Per-channel samples per encoded FLAC frame.
+const BLOCK_FRAMES: usize = 4096;
+
+/// How many callback buffers may queue up before the callback drops audio
+/// instead of blocking.
+pub(crate) const QUEUE_DEPTH: usize = 64;
+
+/// Byte offset of the STREAMINFO body in the file: the `fLaC` magic plus
+/// the 4-byte metadata block header.
+const STREAMINFO_OFFSET: u64 = 8;
+
+/// A running FLAC encoder thread. Dropping every sender closes the
+/// channel; `finish` then joins the thread and returns the sample count.
+pub(crate) struct FlacWorker {
+    handle: JoinHandle<Result<u64, Error>>,
+}
+
+impl FlacWorker {
+    /// Waits for the encoder to drain its queue, finish the file, and
+    /// rewrite the header. Returns the number of samples encoded, summed
+    /// over channels like hound's `len`. Call only after the capture
+    /// stream (and with it the callback's sender) has been dropped,
+    /// otherwise this blocks until it is.
+    pub(crate) fn finish(self) -> Result<u64, Error> {
+        self.handle
+            .join()
+            .map_err(|_| anyhow!("flac encoder thread panicked"))?
+    }
+}
+
+/// Spawns the encoder thread for one output file. The wav spec carries
+/// the already-resolved channel count, sample rate, and bit depth; float
+/// capture is encoded at 24 bits, which covers the precision of any ADC
+/// we record from.
+pub(crate) fn spawn_worker(
+    rx: Receiver<Vec<f32>>,
+    path: String,
+    spec: &WavSpec,
+) -> Result<FlacWorker, Error> {
+    let bits_per_sample = match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, _) => 24,
+        (_, bits) => bits as usize,
+    };
+    let stream_info = StreamInfo::new(
+        spec.sample_rate as usize,
+        spec.channels as usize,
+        bits_per_sample,
+    )
+    .map_err(|err| anyhow!("unsupported flac stream parameters: {}", err))?;
+    let config = Encoder::default()
+        .into_verified()
+        .map_err(|(_, err)| anyhow!("invalid flac encoder configuration: {}", err))?;
+    let file = BufWriter::new(File::create(&path)?);
+    let channels = spec.channels as usize;
+    let handle = thread::spawn(move || {
+        run_worker(rx, file, &config, stream_info, channels, bits_per_sample)
+    });
+    Ok(FlacWorker { handle })
+}
+
+fn run_worker(
+    rx: Receiver<Vec<f32>>,
+    mut file: BufWriter<File>,
+    config: &flacenc::error::Verified<Encoder>,
+    mut stream_info: StreamInfo,
+    channels: usize,
+    bits_per_sample: usize,
+) -> Result<u64, Error> {
+    write_header(&mut file, &stream_info)?;
+    let mut framebuf = FrameBuf::with_size(channels, BLOCK_FRAMES)
+        .map_err(|err| anyhow!("flac frame buffer setup failed: {}", err))?;
+    // Normalized samples scale to the largest magnitude the bit depth can
+    // carry, mirroring what cpal's integer conversions produce.
+    let scale = ((1u32 << (bits_per_sample - 1)) - 1) as f32;
+    let block_samples = BLOCK_FRAMES * channels;
+    let mut pending: Vec<i32> = Vec::with_capacity(block_samples);
+    let mut frame_number = 0usize;
+    let mut samples_written = 0u64;
+    for buffer in rx {
+        samples_written += buffer.len() as u64;
+        pending.extend(
+            buffer
+                .iter()
+                .map(|sample| (sample.clamp(-1.0, 1.0) * scale) as i32),
+        );
+        while pending.len() >= block_samples {
+            let rest = pending.split_off(block_samples);
+            encode_block(
+                &pending,
+                &mut framebuf,
+                config,
+                &mut stream_info,
+                frame_number,
+                &mut file,
+            )?;
+            pending = rest;
+            frame_number += 1;
+        }
+    }
+    // The last partial block is padded with silence: FLAC frames carry a
+    // frame number rather than a sample offset, so a short block anywhere
+    // but the very end would desynchronize seeking.
+    if !pending.is_empty() {
+        pending.resize(block_samples, 0);
+        encode_block(
+            &pending,
+            &mut framebuf,
+            config,
+            &mut stream_info,
+            frame_number,
+            &mut file,
+        )?;
+    }
+    file.seek(SeekFrom::Start(STREAMINFO_OFFSET))?;
+    write_bits(&stream_info, &mut file)?;
+    file.flush()?;
+    Ok(samples_written)
+}
+
+/// Writes the `fLaC` magic and the STREAMINFO metadata block. The block
+/// body still carries zero counts at this point and is rewritten in place
+/// once the totals are known; the MD5 field is left zeroed, which the
+/// format defines as "not computed".
+fn write_header(file: &mut BufWriter<File>, stream_info: &StreamInfo) -> Result<(), Error> {
+    file.write_all(b"fLaC")?;
+    // Block header: last-block flag plus type 0 (STREAMINFO), then the
+    // 24-bit body length.
+    let body_len = (stream_info.count_bits() / 8) as u32;
+    file.write_all(&[0x80])?;
+    file.write_all(&body_len.to_be_bytes()[1..])?;
+    write_bits(stream_info, file)
+}
+
+/// Encodes one full block of interleaved samples and appends the frame.
+fn encode_block(
+    interleaved: &[i32],
+    framebuf: &mut FrameBuf,
+    config: &flacenc::error::Verified<Encoder>,
+    stream_info: &mut StreamInfo,
+    frame_number: usize,
+    file: &mut BufWriter<File>,
+) -> Result<(), Error> {
+    framebuf
+        .fill_interleaved(interleaved)
+        .map_err(|err| anyhow!("filling flac frame buffer failed: {}", err))?;
+    let frame = encode_fixed_size_frame(config, framebuf, frame_number, stream_info)
+        .map_err(|err| anyhow!("flac encoding failed: {:?}", err))?;
+    stream_info.update_frame_info(&frame);
+    write_bits(&frame, file)
+}
+
+/// Serializes a flacenc component and writes it out. Every component we
+/// write (STREAMINFO, whole frames) is byte-aligned by the format.
+fn write_bits(component: &impl BitRepr, file: &mut BufWriter<File>) -> Result<(), Error> {
+    let mut sink = ByteSink::new();
+    component
+        .write(&mut sink)
+        .map_err(|err| anyhow!("serializing flac data failed: {}", err))?;
+    file.write_all(sink.as_slice())?;
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use anyhow::Error;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host, HostId, SampleRate, StreamConfig, SupportedStreamConfig};
+use serde::Serialize;
+
+use crate::error::RecorderError;
+
+pub fn get_host(host_id: HostId) -> Result<Host, Error> {
+    let host_id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| *id == host_id)
+        .ok_or_else(|| RecorderError::HostUnavailable(format!("{:?}", host_id)))?;
+    Ok(cpal::host_from_id(host_id)?)
+}
+
+pub fn get_device(host: Host, device_name: Option<String>) -> Result<Device, Error> {
+    match device_name {
+        Some(name) => {
+            let mut available = Vec::new();
+            for device in host.input_devices()? {
+                match device.name() {
+                    Ok(device_name) if device_name == name => return Ok(device),
+                    Ok(device_name) => available.push(device_name),
+                    Err(_) => continue,
+                }
+            }
+            Err(RecorderError::DeviceNotFound { name, available }.into())
+        }
+        None => host.default_input_device().ok_or_else(|| {
+            RecorderError::NoDefaultDevice(format!("{:?}", host.id())).into()
+        }),
+    }
+}
+
+/// Name fragments of onboard inputs — the Pi's HDMI and headphone jack
+/// codecs chief among them — that auto-selection should never pick, since
+/// recording from them instead of the hydrophone yields silence.
+const ONBOARD_NAME_FRAGMENTS: &[&str] = &["bcm2835", "hdmi", "headphones"];
+
+fn is_onboard(lowercase_name: &str) -> bool {
+    ONBOARD_NAME_FRAGMENTS
+        .iter()
+        .any(|fragment| lowercase_name.contains(fragment))
+}
+
+/// Auto-selects an input device for hosts where the default input is the
+/// wrong one, e.g. a Pi whose default is the HDMI capture rather than the
+/// USB hydrophone. Devices whose names contain `preferred`
+/// (case-insensitively, e.g. `"USB"`) win; failing that the first input
+/// that is not a known onboard device is taken with a warning; failing
+/// that the host default is used, also with a warning, so a session that
+/// ended up on the wrong input is at least visible in the logs.
+pub fn get_device_preferring(host: Host, preferred: &str) -> Result<Device, Error> {
+    let needle = preferred.to_lowercase();
+    let mut fallback = None;
+    for device in host.input_devices()? {
+        if let Ok(name) = device.name() {
+            let lowercase = name.to_lowercase();
+            if lowercase.contains(&needle) {
+                log::info!("input device selected: {} (matches \"{}\")", name, preferred);
+                return Ok(device);
+            }
+            if fallback.is_none() && !is_onboard(&lowercase) {
+                fallback = Some((name, device));
+            }
+        }
+    }
+    if let Some((name, device)) = fallback {
+        log::warn!(
+            "no input device matches \"{}\"; selected {} instead",
+            preferred,
+            name
+        );
+        return Ok(device);
+    }
+    log::warn!(
+        "no input device matches \"{}\" and every input looks onboard; \
+         falling back to the host default",
+        preferred
+    );
+    host.default_input_device()
+        .ok_or_else(|| RecorderError::NoDefaultDevice(format!("{:?}", host.id())).into())
+}
+
+/// Tries each candidate device name in order and returns the first one
+/// present, for redundant hardware where a backup interface should stand
+/// in when the primary fails to enumerate. The selection is logged so a
+/// session running on the backup is visible in the logs. When none of
+/// the candidates are present the error lists them all alongside the
+/// devices that were found.
+pub fn get_device_from_candidates(host: Host, candidates: &[String]) -> Result<Device, Error> {
+    let mut available = Vec::new();
+    let mut devices = Vec::new();
+    for device in host.input_devices()? {
+        if let Ok(name) = device.name() {
+            available.push(name.clone());
+            devices.push((name, device));
+        }
+    }
+    for (rank, candidate) in candidates.iter().enumerate() {
+        if let Some(position) = devices.iter().position(|(name, _)| name == candidate) {
+            let (name, device) = devices.swap_remove(position);
+            if rank == 0 {
+                log::info!("input device selected: {}", name);
+            } else {
+                log::warn!(
+                    "input device selected: {} (candidate {} of {}; preferred device missing)",
+                    name,
+                    rank + 1,
+                    candidates.len()
+                );
+            }
+            return Ok(device);
+        }
+    }
+    Err(RecorderError::DeviceNotFound {
+        name: candidates.join(", "),
+        available,
+    }
+    .into())
+}
+
+pub fn get_default_config(device: &Device) -> Result<SupportedStreamConfig, Error> {
+    Ok(device.default_input_config()?)
+}
+
+/// Builds the capture config after checking the requested rate and channel
+/// count against the ranges the device reports, so an unsupported request
+/// fails here with the supported ranges listed instead of as an opaque
+/// stream error deep in `create_stream`.
+pub fn get_user_config(
+    device: &Device,
+    sample_rate: u32,
+    channels: u16,
+    buffer_size: u32,
+) -> Result<StreamConfig, Error> {
+    get_user_config_with_periods(device, sample_rate, channels, buffer_size, None)
+}
+
+/// Like [`get_user_config`], but additionally sizes the stream for a
+/// number of periods. cpal does not expose period counts directly on any
+/// backend, so the request is honoured by asking for a buffer of
+/// `periods * buffer_size` frames — on ALSA that drives the same
+/// underrun headroom an explicit period count would — and a warning notes
+/// the approximation. `None` keeps the plain single-buffer behavior.
+pub fn get_user_config_with_periods(
+    device: &Device,
+    sample_rate: u32,
+    channels: u16,
+    buffer_size: u32,
+    periods: Option<u32>,
+) -> Result<StreamConfig, Error> {
+    let supported: Vec<_> = device.supported_input_configs()?.collect();
+    let matches = supported.iter().any(|range| {
+        range.channels() == channels
+            && range.min_sample_rate().0 <= sample_rate
+            && sample_rate <= range.max_sample_rate().0
+    });
+    if !matches {
+        let ranges: Vec<String> = supported
+            .iter()
+            .map(|range| {
+                format!(
+                    "{} ch at {}-{} Hz",
+                    range.channels(),
+                    range.min_sample_rate().0,
+                    range.max_sample_rate().0
+                )
+            })
+            .collect();
+        return Err(RecorderError::UnsupportedConfig(format!(
+            "device does not support {} channels at {} Hz, supported configs: {}",
+            channels,
+            sample_rate,
+            ranges.join(", ")
+        ))
+        .into());
+    }
+    let frames = match periods {
+        Some(periods) => {
+            log::warn!(
+                "backend does not expose period counts; requesting a {}-frame buffer \
+                 ({} periods of {} frames) instead",
+                buffer_size * periods,
+                periods,
+                buffer_size
+            );
+            buffer_size * periods
+        }
+        None => buffer_size,
+    };
+    Ok(StreamConfig {
+        channels,
+        sample_rate: SampleRate(sample_rate),
+        buffer_size: cpal::BufferSize::Fixed(frames),
+    })
+}
+
+/// One capture configuration range a device supports, flattened into
+/// plain serializable fields so a setup wizard can list valid options
+/// without touching cpal types.
+#[derive(Clone, Debug, Serialize)]
+pub struct SupportedConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    /// cpal's name for the sample format, e.g. `i16` or `f32`.
+    pub sample_format: String,
+}
+
+/// Returns every input configuration range the named device (or the
+/// host's default input device) reports, so unsupported rates and channel
+/// counts can be ruled out before a stream ever fails to open.
+pub fn supported_configs(
+    device_name: Option<String>,
+    host_id: HostId,
+) -> Result<Vec<SupportedConfigRange>, Error> {
+    let device = get_device(get_host(host_id)?, device_name)?;
+    Ok(device
+        .supported_input_configs()?
+        .map(|range| SupportedConfigRange {
+            channels: range.channels(),
+            min_sample_rate: range.min_sample_rate().0,
+            max_sample_rate: range.max_sample_rate().0,
+            sample_format: range.sample_format().to_string(),
+        })
+        .collect())
+}
+
+/// Returns the names of all input devices on the given host. Devices whose
+/// name cannot be read are skipped instead of failing the enumeration.
+pub fn list_input_devices(host_id: HostId) -> Result<Vec<String>, Error> {
+    let host = get_host(host_id)?;
+    Ok(host
+        .input_devices()?
+        .filter_map(|device| device.name().ok())
+        .collect())
+}
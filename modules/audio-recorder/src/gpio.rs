@@ -0,0 +1,134 @@
+//! Raspberry Pi GPIO integration, compiled only with the `rpi` feature.
+//! Enclosures often wire an external hardware trigger (a magnetic switch,
+//! a hydrophone pre-amp gate) to a GPIO pin; this module starts recording
+//! segments while that pin is asserted. It also drives a status LED so a
+//! headless deployment shows at a glance whether it is capturing. The
+//! software interrupt handles keep working throughout, so a console
+//! interrupt or [`StopHandle`] stops a GPIO-triggered session the same
+//! way it stops any other.
+//!
+//! [`StopHandle`]: crate::interrupt::StopHandle
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::Error;
+use rppal::gpio::{Gpio, InputPin, Level};
+
+use crate::recorder::Recorder;
+
+/// How often the trigger pin is polled while idle. Coarse enough to stay
+/// off the CPU budget, fine enough that a human-operated switch feels
+/// immediate.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Half-period of the error blink, and how often the LED thread rereads
+/// the recorder state.
+const LED_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A hardware trigger input: the configured BCM pin with the internal
+/// pull-down enabled, so a floating input reads as not asserted.
+pub struct GpioTrigger {
+    pin: InputPin,
+}
+
+impl GpioTrigger {
+    /// Claims the given BCM pin as a pulled-down input. Fails when the
+    /// GPIO peripheral is unavailable or the pin is already in use.
+    pub fn new(pin: u8) -> Result<Self, Error> {
+        let pin = Gpio::new()?.get(pin)?.into_input_pulldown();
+        Ok(GpioTrigger { pin })
+    }
+
+    /// Returns true while the trigger pin reads high.
+    pub fn is_asserted(&self) -> bool {
+        self.pin.read() == Level::High
+    }
+}
+
+/// A status LED on a GPIO pin, lit while the recorder captures or
+/// finalizes and blinking while the capture device is lost and the
+/// recorder waits to reconnect. The LED is driven from a background
+/// thread watching the recorder's state transitions; dropping the handle
+/// stops the thread and turns the LED off.
+pub struct StatusLed {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StatusLed {
+    /// Claims the given BCM pin as an output (initially low) and starts
+    /// the LED thread watching `rec`. The handle is independent of the
+    /// recorder borrow, so recording can proceed while the LED runs.
+    pub fn attach(rec: &Recorder, pin: u8) -> Result<Self, Error> {
+        let mut pin = Gpio::new()?.get(pin)?.into_output_low();
+        let state = rec.state_handle();
+        let device_lost = rec.device_lost_handle();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut blink = false;
+            while !thread_stop.load(Ordering::SeqCst) {
+                let lit = if device_lost.load(Ordering::SeqCst) {
+                    blink = !blink;
+                    blink
+                } else {
+                    // Recording (1) and Finalizing (3); Idle and Paused
+                    // leave the LED dark.
+                    matches!(state.load(Ordering::SeqCst), 1 | 3)
+                };
+                if lit {
+                    pin.set_high();
+                } else {
+                    pin.set_low();
+                }
+                thread::sleep(LED_INTERVAL);
+            }
+            pin.set_low();
+        });
+        Ok(StatusLed {
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for StatusLed {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Records `secs`-long segments while the trigger pin on `pin` is high,
+/// idling (with the pin polled and interrupts honoured) while it is low.
+/// Runs until interrupted or the disk fills, finishing the segment in
+/// progress first. Returns the paths of the finalized files.
+pub fn gpio_triggered_recording(
+    rec: &mut Recorder,
+    pin: u8,
+    secs: u64,
+) -> Result<Vec<PathBuf>, Error> {
+    let trigger = GpioTrigger::new(pin)?;
+    let mut files = Vec::new();
+    loop {
+        while !trigger.is_asserted() {
+            if rec.is_interrupted() {
+                rec.log_summary();
+                return Ok(files);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        rec.record_secs(secs)?;
+        files.extend(rec.current_file());
+        if rec.is_interrupted() || rec.low_disk() {
+            rec.log_summary();
+            return Ok(files);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! High-pass filtering for hydrophone capture. Underwater recordings are
+//! dominated by DC offset from the ADC and low-frequency flow noise, both
+//! of which eat headroom and skew downstream analysis.
+//!
+//! The filter is a second-order Butterworth high-pass biquad (RBJ audio
+//! EQ cookbook coefficients): -3 dB at the configured cutoff, rolling off
+//! at 12 dB per octave below it, with flat passband response above. State
+//! is kept per channel and carried across callback buffers, so there are
+//! no discontinuities at buffer boundaries. Processing runs in f64 to
+//! keep the recursive state well-conditioned at low cutoff frequencies.
+
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// Per-channel direct-form-I biquad state: the two previous input and
+/// output samples.
+#[derive(Clone, Copy, Default)]
+struct ChannelState {
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+pub(crate) struct HighPass {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    channels: usize,
+    state: Vec<ChannelState>,
+}
+
+impl HighPass {
+    /// Builds the filter for the given cutoff and capture configuration.
+    /// The caller validates that the cutoff lies below Nyquist.
+    pub(crate) fn new(cutoff_hz: f32, sample_rate: u32, channels: u16) -> Self {
+        let w0 = 2.0 * PI * f64::from(cutoff_hz) / f64::from(sample_rate);
+        let alpha = w0.sin() / (2.0 * FRAC_1_SQRT_2);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: (1.0 + cos_w0) / 2.0 / a0,
+            b1: -(1.0 + cos_w0) / a0,
+            b2: (1.0 + cos_w0) / 2.0 / a0,
+            a1: -2.0 * cos_w0 / a0,
+            a2: (1.0 - alpha) / a0,
+            channels: channels as usize,
+            state: vec![ChannelState::default(); channels as usize],
+        }
+    }
+
+    /// Filters one interleaved buffer in place, advancing the per-channel
+    /// state so the next buffer continues seamlessly.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        for (index, sample) in samples.iter_mut().enumerate() {
+            let state = &mut self.state[index % self.channels];
+            let x = f64::from(*sample);
+            let y = self.b0 * x + self.b1 * state.x1 + self.b2 * state.x2
+                - self.a1 * state.y1
+                - self.a2 * state.y2;
+            state.x2 = state.x1;
+            state.x1 = x;
+            state.y2 = state.y1;
+            state.y1 = y;
+            *sample = y as f32;
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use anyhow::Error;
+
+/// Handles for waiting on an interrupt from the control thread. With the
+/// ctrlc `termination` feature this covers SIGINT, SIGTERM, and SIGHUP on
+/// Unix, so a systemd stop finalizes the file just like Ctrl+C does. Other
+/// platforms keep their native console interrupt behavior.
+#[derive(Clone)]
+pub struct InterruptHandles {
+    interrupted: Arc<AtomicBool>,
+    pair: Arc<(Mutex<bool>, Condvar)>,
+}
+
+/// Cloneable handle that stops a recording the same way a console
+/// interrupt does, for embedders that drive the recorder from a UI or
+/// control server instead of sending signals.
+#[derive(Clone)]
+pub struct StopHandle {
+    interrupted: Arc<AtomicBool>,
+    pair: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl StopHandle {
+    /// Triggers the interrupt, waking any thread blocked in `stream_wait`.
+    pub fn stop(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+        let (lock, cvar) = &*self.pair;
+        let mut stop = lock.lock().unwrap();
+        *stop = true;
+        cvar.notify_all();
+    }
+}
+
+impl InterruptHandles {
+    pub fn new() -> Result<Self, Error> {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let pair = Arc::new((Mutex::new(false), Condvar::new()));
+        let handle = StopHandle {
+            interrupted: Arc::clone(&interrupted),
+            pair: Arc::clone(&pair),
+        };
+        ctrlc::set_handler(move || handle.stop())?;
+        Ok(Self { interrupted, pair })
+    }
+
+    /// Returns a handle that can stop the recording programmatically.
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle {
+            interrupted: Arc::clone(&self.interrupted),
+            pair: Arc::clone(&self.pair),
+        }
+    }
+
+    /// Blocks the calling thread until an interrupt arrives.
+    pub fn stream_wait(&self) {
+        let (lock, cvar) = &*self.pair;
+        let mut stop = lock.lock().unwrap();
+        while !*stop {
+            stop = cvar.wait(stop).unwrap();
+        }
+    }
+
+    /// Blocks the calling thread until an interrupt arrives or `timeout`
+    /// elapses. Returns true if the wait ended due to an interrupt.
+    pub fn stream_wait_timeout(&self, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.pair;
+        let stop = lock.lock().unwrap();
+        let (stop, _) = cvar
+            .wait_timeout_while(stop, timeout, |stop| !*stop)
+            .unwrap();
+        *stop
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,182 @@
+pub mod async_recorder;
+pub mod chunks;
+pub mod config;
+mod decimate;
+pub mod error;
+mod flac;
+pub mod getters;
+#[cfg(feature = "rpi")]
+pub mod gpio;
+mod highpass;
+pub mod interrupt;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod multi;
+mod normalize;
+mod ogg_opus;
+mod ogg_vorbis;
+pub mod raw_pcm;
+pub mod recorder;
+mod resample;
+pub mod spectrogram;
+pub mod spectrum;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use recorder::{BatchState, Recorder};
+
+/// Records a batch of `secs`-long files until interrupted. An interrupt
+/// finishes the file currently being recorded before stopping. Returns the
+/// paths of the finalized files so e.g. an upload daemon knows exactly
+/// what was produced.
+pub fn batch_recording(rec: &mut Recorder, secs: u64) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    loop {
+        if rec.wait_for_window() {
+            rec.log_summary();
+            return Ok(files);
+        }
+        rec.record_secs(secs)?;
+        files.extend(rec.current_file());
+        if rec.is_interrupted() || rec.low_disk() {
+            rec.log_summary();
+            return Ok(files);
+        }
+    }
+}
+
+/// Records exactly `max_files` segments of `secs` seconds each, for
+/// time-boxed experiments that should not need babysitting. An interrupt
+/// or low disk space still stops the batch early, finishing the segment
+/// in progress first. With batch persistence enabled, segments recorded
+/// by earlier runs count toward `max_files`, so a reboot mid-experiment
+/// resumes rather than restarts. Returns the paths of the files
+/// finalized by this run.
+pub fn batch_recording_n(
+    rec: &mut Recorder,
+    secs: u64,
+    max_files: usize,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut state = if rec.batch_persistence() {
+        rec.load_batch_state()
+    } else {
+        BatchState::default()
+    };
+    let mut files = Vec::new();
+    while (state.files as usize) < max_files {
+        if rec.wait_for_window() {
+            break;
+        }
+        rec.record_secs(secs)?;
+        files.extend(rec.current_file());
+        state.files += 1;
+        state.elapsed_secs += secs;
+        if rec.batch_persistence() {
+            rec.save_batch_state(state);
+        }
+        if rec.is_interrupted() || rec.low_disk() {
+            break;
+        }
+    }
+    if rec.batch_persistence() && state.files as usize >= max_files {
+        rec.clear_batch_state();
+    }
+    rec.log_summary();
+    Ok(files)
+}
+
+/// Records `secs`-long segments until roughly `total_secs` of wall-clock
+/// time has passed, e.g. two-minute files for up to six hours. The budget
+/// is measured against the wall clock rather than summing segment
+/// lengths, so time lost to finalizing and reopening files counts too. A
+/// segment in progress when the budget runs out is finished, not cut
+/// short, matching how interrupts are handled. With batch persistence
+/// enabled, wall-clock time spent by earlier runs counts against the
+/// budget, so a reboot mid-deployment resumes with what is left. Returns
+/// the paths of the files finalized by this run.
+pub fn batch_recording_for(
+    rec: &mut Recorder,
+    secs: u64,
+    total_secs: u64,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut state = if rec.batch_persistence() {
+        rec.load_batch_state()
+    } else {
+        BatchState::default()
+    };
+    if state.elapsed_secs >= total_secs {
+        if rec.batch_persistence() {
+            rec.clear_batch_state();
+        }
+        rec.log_summary();
+        return Ok(Vec::new());
+    }
+    let deadline = Instant::now() + Duration::from_secs(total_secs - state.elapsed_secs);
+    let mut files = Vec::new();
+    while Instant::now() < deadline {
+        if rec.wait_for_window() {
+            break;
+        }
+        let started = Instant::now();
+        rec.record_secs(secs)?;
+        files.extend(rec.current_file());
+        state.files += 1;
+        state.elapsed_secs += started.elapsed().as_secs();
+        if rec.batch_persistence() {
+            rec.save_batch_state(state);
+        }
+        if rec.is_interrupted() || rec.low_disk() {
+            break;
+        }
+    }
+    if rec.batch_persistence() && Instant::now() >= deadline {
+        rec.clear_batch_state();
+    }
+    rec.log_summary();
+    Ok(files)
+}
+
+/// Records `record_secs`-long files with `sleep_secs` of idle time between
+/// them, until interrupted. The stream and device are released for the
+/// whole sleep interval to save power on battery deployments, and each
+/// segment gets its own timestamped file. Returns the paths of the
+/// finalized files.
+pub fn duty_cycle_recording(
+    rec: &mut Recorder,
+    record_secs: u64,
+    sleep_secs: u64,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    loop {
+        if rec.wait_for_window() {
+            rec.log_summary();
+            return Ok(files);
+        }
+        rec.record_secs(record_secs)?;
+        files.extend(rec.current_file());
+        if rec.is_interrupted() || rec.low_disk() {
+            rec.log_summary();
+            return Ok(files);
+        }
+        if rec.sleep_interruptible(sleep_secs) {
+            rec.log_summary();
+            return Ok(files);
+        }
+    }
+}
+
+/// Records a batch of `secs`-long files from a single uninterrupted cpal
+/// stream, so consecutive files butt together sample-exact. Use this over
+/// [`batch_recording`] when cross-segment analysis needs continuity;
+/// plain batching releases the device between segments and loses a few
+/// buffers each time.
+pub fn gapless_batch_recording(rec: &mut Recorder, secs: u64) -> Result<Vec<PathBuf>, Error> {
+    rec.record_gapless(secs)
+}
+
+/// Records a single file until interrupted.
+pub fn contiguous_recording(rec: &mut Recorder) -> Result<(), Error> {
+    Ok(rec.record()?)
+}
@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use anyhow::Error;
+use clap::Parser;
+
+use audio_recorder::getters::list_input_devices;
+use audio_recorder::recorder::Recorder;
+use audio_recorder::{batch_recording, contiguous_recording};
+
+#[derive(Parser)]
+#[clap(version = "0.1.0", author = "Satu Koskinen")]
+struct Cli {
+    /// Name prefix for recorded files
+    #[arg(long, default_value = "audio")]
+    name: String,
+
+    /// Directory to write recordings to
+    #[arg(long, default_value = "/output/audio")]
+    output: PathBuf,
+
+    /// Sample rate in Hz
+    #[arg(long, default_value_t = 44100)]
+    sample_rate: u32,
+
+    /// Number of channels to record
+    #[arg(long, default_value_t = 2)]
+    channels: u16,
+
+    /// Stream buffer size in frames
+    #[arg(long, default_value_t = 1024)]
+    buffer_size: u32,
+
+    /// Length of one file in seconds; a single contiguous file is recorded
+    /// when not set
+    #[arg(long)]
+    batch_secs: Option<u64>,
+
+    /// Split the contiguous recording into a new file every given number of
+    /// seconds while keeping the stream running
+    #[arg(long, conflicts_with = "batch_secs")]
+    split_secs: Option<u64>,
+
+    /// Name of the input device to record from; the host's default input
+    /// device is used when not set
+    #[arg(long)]
+    device: Option<String>,
+
+    /// List the input devices available on the default host and exit
+    #[arg(long)]
+    list_devices: bool,
+}
+
+fn main() -> Result<(), Error> {
+    // Show the REC/STOP progress messages by default; RUST_LOG overrides.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Cli::parse();
+    let host = cpal::default_host().id();
+
+    if args.list_devices {
+        for name in list_input_devices(host)? {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+
+    let mut recorder = Recorder::init(
+        args.name,
+        args.output,
+        host,
+        args.sample_rate,
+        args.channels,
+        args.buffer_size,
+        args.device,
+    )?;
+    match (args.batch_secs, args.split_secs) {
+        (Some(secs), _) => batch_recording(&mut recorder, secs).map(|_| ()),
+        (None, Some(secs)) => recorder.record_with_split(secs),
+        (None, None) => contiguous_recording(&mut recorder),
+    }
+}
@@ -0,0 +1,98 @@
+//! MQTT status publishing, compiled only with the `mqtt` feature. A fleet
+//! of buoys reports to a central broker, so each logger publishes its
+//! file lifecycle and health counters to a per-device topic instead of
+//! requiring an SSH session to check on. Publishing rides on the event
+//! callback system and the broker connection runs on its own thread:
+//! losing the broker costs status messages, never audio.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Error;
+use rumqttc::{Client, MqttOptions, QoS};
+
+use crate::recorder::{free_bytes, Recorder, RecorderEvent};
+
+/// How many unsent messages the client buffers before `try_publish`
+/// starts failing; failures are logged and dropped.
+const QUEUE_DEPTH: usize = 16;
+
+/// How long the connection thread backs off after a broker error before
+/// the client retries.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Shared health counters snapshot into every published payload. Built by
+/// the recorder, carried by the publishing callback.
+pub struct Telemetry {
+    pub(crate) dropped: Arc<AtomicU64>,
+    pub(crate) reconnects: Arc<AtomicU32>,
+    pub(crate) path: PathBuf,
+}
+
+impl Telemetry {
+    /// Renders one event as the JSON payload published to the topic,
+    /// folding in the counters a fleet dashboard wants alongside it.
+    fn payload(&self, event: &RecorderEvent) -> Vec<u8> {
+        let body = match event {
+            RecorderEvent::FileStarted { path, timestamp } => serde_json::json!({
+                "event": "file_started",
+                "path": path,
+                "timestamp": timestamp.to_rfc3339(),
+                "dropped_samples": self.dropped.load(Ordering::Relaxed),
+                "reconnects": self.reconnects.load(Ordering::Relaxed),
+                "free_bytes": free_bytes(&self.path).ok(),
+            }),
+            RecorderEvent::FileStopped {
+                path,
+                samples_written,
+                duration,
+                checksum,
+            } => serde_json::json!({
+                "event": "file_stopped",
+                "path": path,
+                "samples_written": samples_written,
+                "duration_secs": duration.as_secs_f64(),
+                "checksum": checksum,
+                "dropped_samples": self.dropped.load(Ordering::Relaxed),
+                "reconnects": self.reconnects.load(Ordering::Relaxed),
+                "free_bytes": free_bytes(&self.path).ok(),
+            }),
+        };
+        body.to_string().into_bytes()
+    }
+}
+
+/// Connects to the broker at `host:port` and publishes every recorder
+/// event to `topic` as JSON. Installs the recorder's event callback, so
+/// combine any additional event handling inside one callback rather than
+/// calling this alongside `set_event_callback`. The broker connection
+/// retries forever in the background; publish failures while disconnected
+/// are logged and dropped.
+pub fn attach(rec: &mut Recorder, host: &str, port: u16, topic: String) -> Result<(), Error> {
+    let client_id = format!("{}-{}", env!("CARGO_PKG_NAME"), std::process::id());
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    let (client, mut connection) = Client::new(options, QUEUE_DEPTH);
+    // The connection event loop must be polled for the client to make
+    // progress; errors here mean the broker is unreachable, which the
+    // recorder does not care about.
+    thread::spawn(move || {
+        for event in connection.iter() {
+            if let Err(err) = event {
+                log::warn!("mqtt connection error: {}", err);
+                thread::sleep(RETRY_DELAY);
+            }
+        }
+    });
+    let telemetry = rec.telemetry();
+    rec.set_event_callback(move |event| {
+        let payload = telemetry.payload(&event);
+        if let Err(err) = client.try_publish(&topic, QoS::AtLeastOnce, false, payload) {
+            log::warn!("mqtt publish failed: {}", err);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Parallel capture from several devices, for setups with multiple
+//! hydrophones on separate USB interfaces. Each device records into its
+//! own wav file; all files of a session share one timestamp prefix, one
+//! interrupt stops every stream, and the start skew between the first and
+//! last stream is logged so users can judge synchronization quality.
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{anyhow, Error};
+use chrono::Local;
+
+use crate::interrupt::InterruptHandles;
+use crate::recorder::{Recorder, RecorderBuilder, SIZE_CHECK_INTERVAL};
+
+pub struct MultiRecorder {
+    recorders: Vec<Recorder>,
+    interrupts: InterruptHandles,
+}
+
+impl MultiRecorder {
+    /// Builds one recorder per prepared builder. The recorders share a
+    /// single interrupt handler — the process-wide signal handler can only
+    /// be installed once — so one Ctrl+C finalizes every writer.
+    pub fn new(builders: Vec<RecorderBuilder>) -> Result<Self, Error> {
+        if builders.is_empty() {
+            return Err(anyhow!("MultiRecorder needs at least one recorder"));
+        }
+        let interrupts = InterruptHandles::new()?;
+        let recorders = builders
+            .into_iter()
+            .map(|builder| builder.interrupt_handles(interrupts.clone()).build())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            recorders,
+            interrupts,
+        })
+    }
+
+    /// Records from every device until interrupted, starting the streams
+    /// back to back so capture begins as close together as the backend
+    /// allows. Returns the paths of the finalized files.
+    pub fn record(&mut self) -> Result<Vec<PathBuf>, Error> {
+        let session = Local::now();
+        let mut start_times = Vec::with_capacity(self.recorders.len());
+        for rec in &mut self.recorders {
+            rec.start_session(session)?;
+            start_times.push(Instant::now());
+        }
+        if let (Some(first), Some(last)) = (start_times.first(), start_times.last()) {
+            log::info!(
+                "started {} streams with {:?} skew between first and last",
+                start_times.len(),
+                last.duration_since(*first)
+            );
+        }
+        loop {
+            if self.interrupts.stream_wait_timeout(SIZE_CHECK_INTERVAL) {
+                break;
+            }
+            for rec in &mut self.recorders {
+                rec.roll_if_needed()?;
+            }
+        }
+        let mut files = Vec::new();
+        for rec in &mut self.recorders {
+            rec.finish_session()?;
+            files.extend(rec.current_file());
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,155 @@
+//! Post-finalize peak normalization, for deployments that want every
+//! file at a consistent listening level. The data chunk is rewritten in
+//! place in two streaming passes — one to find the peak, one to apply
+//! the gain — so memory stays bounded for multi-gigabyte files on
+//! Pi-class hardware and the metadata chunks appended at finalize
+//! survive untouched. Runs inside the finalize sequence, ahead of the
+//! checksum, so recorded hashes describe the normalized data.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use anyhow::{anyhow, Error};
+use hound::WavSpec;
+
+/// Bytes processed per read/write during the streaming passes.
+const BLOCK_BYTES: usize = 64 * 1024;
+
+/// Normalizes the file at `path` so its peak hits `target_peak`.
+pub(crate) fn normalize(path: &str, target_peak: f32) -> Result<(), Error> {
+    let spec = hound::WavReader::open(path)?.spec();
+    let mut file = File::options().read(true).write(true).open(path)?;
+    let (data_start, data_len) = find_data_chunk(&mut file)?;
+    let peak = scan_peak(&mut file, data_start, data_len, &spec)?;
+    if peak == 0.0 {
+        return Err(anyhow!("file is silent; nothing to normalize"));
+    }
+    apply_gain(
+        &mut file,
+        data_start,
+        data_len,
+        &spec,
+        f64::from(target_peak) / peak,
+    )?;
+    log::info!("NORMALIZE: {} (target peak {:.3})", path, target_peak);
+    Ok(())
+}
+
+/// Walks the RIFF structure to the `data` chunk and returns the byte
+/// offset of its payload and the payload length.
+fn find_data_chunk(file: &mut File) -> Result<(u64, u64), Error> {
+    let mut riff = [0u8; 12];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut riff)?;
+    if &riff[..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(anyhow!("missing RIFF/WAVE header"));
+    }
+    loop {
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let size = u64::from(u32::from_le_bytes([header[4], header[5], header[6], header[7]]));
+        if &header[..4] == b"data" {
+            return Ok((file.stream_position()?, size));
+        }
+        // Chunks are word-aligned; odd sizes carry a padding byte.
+        file.seek(SeekFrom::Current((size + size % 2) as i64))?;
+    }
+}
+
+/// First pass: the largest absolute sample value, at full scale 1.0.
+fn scan_peak(file: &mut File, start: u64, len: u64, spec: &WavSpec) -> Result<f64, Error> {
+    let width = sample_width(spec)?;
+    let mut remaining = len - len % width as u64;
+    let mut block = vec![0u8; BLOCK_BYTES - BLOCK_BYTES % width];
+    let mut peak = 0.0f64;
+    file.seek(SeekFrom::Start(start))?;
+    while remaining > 0 {
+        let take = block.len().min(remaining as usize);
+        file.read_exact(&mut block[..take])?;
+        for sample in block[..take].chunks_exact(width) {
+            peak = peak.max(decode(sample, spec).abs());
+        }
+        remaining -= take as u64;
+    }
+    Ok(peak)
+}
+
+/// Second pass: scales every sample by `gain`, writing each block back
+/// over the bytes it was read from.
+fn apply_gain(
+    file: &mut File,
+    start: u64,
+    len: u64,
+    spec: &WavSpec,
+    gain: f64,
+) -> Result<(), Error> {
+    let width = sample_width(spec)?;
+    let mut remaining = len - len % width as u64;
+    let mut offset = start;
+    let mut block = vec![0u8; BLOCK_BYTES - BLOCK_BYTES % width];
+    while remaining > 0 {
+        let take = block.len().min(remaining as usize);
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut block[..take])?;
+        for sample in block[..take].chunks_exact_mut(width) {
+            let scaled = decode(sample, spec) * gain;
+            encode(scaled, sample, spec);
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&block[..take])?;
+        offset += take as u64;
+        remaining -= take as u64;
+    }
+    Ok(())
+}
+
+fn sample_width(spec: &WavSpec) -> Result<usize, Error> {
+    match spec.bits_per_sample {
+        8 | 16 | 24 | 32 => Ok(usize::from(spec.bits_per_sample / 8)),
+        bits => Err(anyhow!("cannot normalize {}-bit samples", bits)),
+    }
+}
+
+fn full_scale(bits: u16) -> f64 {
+    (1i64 << (bits - 1)) as f64
+}
+
+/// Reads one little-endian sample as a full-scale-1.0 value. 8-bit wav
+/// data is unsigned with the midpoint at 0x80; everything wider is
+/// signed.
+fn decode(bytes: &[u8], spec: &WavSpec) -> f64 {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, _) => f64::from(f32::from_le_bytes(
+            bytes.try_into().expect("four-byte float sample"),
+        )),
+        (hound::SampleFormat::Int, bits) => {
+            let raw = match bits {
+                8 => i64::from(bytes[0]) - 0x80,
+                16 => i64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+                24 => i64::from((i32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) << 8) >> 8),
+                _ => i64::from(i32::from_le_bytes(
+                    bytes.try_into().expect("four-byte int sample"),
+                )),
+            };
+            raw as f64 / full_scale(bits)
+        }
+    }
+}
+
+/// Writes one full-scale-1.0 value back in the spec's width, clamping
+/// integer samples at the format's limits.
+fn encode(value: f64, bytes: &mut [u8], spec: &WavSpec) {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, _) => bytes.copy_from_slice(&(value as f32).to_le_bytes()),
+        (hound::SampleFormat::Int, bits) => {
+            let full = full_scale(bits);
+            let raw = (value * full).round().clamp(-full, full - 1.0) as i64;
+            match bits {
+                8 => bytes[0] = (raw + 0x80) as u8,
+                16 => bytes.copy_from_slice(&(raw as i16).to_le_bytes()),
+                24 => bytes.copy_from_slice(&(raw as i32).to_le_bytes()[..3]),
+                _ => bytes.copy_from_slice(&(raw as i32).to_le_bytes()),
+            }
+        }
+    }
+}
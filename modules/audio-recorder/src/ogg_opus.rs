@@ -0,0 +1,161 @@
+//! Ogg-Opus encoding worker, for shipping recordings off remote buoys
+//! over constrained cellular links. Opus is lossy — this mode exists for
+//! low-bitrate monitoring, not archival — so the bitrate is a knob rather
+//! than a constant. Like the FLAC worker, the audio callback hands
+//! processed buffers over a bounded channel and the encoder runs on its
+//! own thread, never inline with capture.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{anyhow, Error};
+use hound::WavSpec;
+use ogg::{PacketWriteEndInfo, PacketWriter};
+
+/// How many callback buffers may queue up before the callback drops audio
+/// instead of blocking.
+pub(crate) const QUEUE_DEPTH: usize = 64;
+
+/// Per-channel samples per Opus frame: 20 ms, the codec's sweet spot.
+const FRAME_FRACTION: u32 = 50;
+
+/// Upper bound for one encoded packet, as recommended by the libopus
+/// documentation.
+const MAX_PACKET_BYTES: usize = 4000;
+
+/// Opus granule positions always count 48 kHz samples, regardless of the
+/// input rate; one 20 ms frame is 960 of them.
+const GRANULE_PER_FRAME: u64 = 960;
+
+/// A running Ogg-Opus encoder thread, joined the same way as the FLAC
+/// worker: drop every sender, then call `finish`.
+pub(crate) struct OpusWorker {
+    handle: JoinHandle<Result<u64, Error>>,
+}
+
+impl OpusWorker {
+    /// Waits for the encoder to drain its queue and close the Ogg stream.
+    /// Returns the number of samples encoded, summed over channels. Call
+    /// only after the capture stream has been dropped.
+    pub(crate) fn finish(self) -> Result<u64, Error> {
+        self.handle
+            .join()
+            .map_err(|_| anyhow!("opus encoder thread panicked"))?
+    }
+}
+
+/// Spawns the encoder thread for one output file. Opus accepts only mono
+/// or stereo input at 8, 12, 16, 24, or 48 kHz, so anything else is
+/// rejected here with the offending value named.
+pub(crate) fn spawn_worker(
+    rx: Receiver<Vec<f32>>,
+    path: String,
+    spec: &WavSpec,
+    bitrate: u32,
+) -> Result<OpusWorker, Error> {
+    let channels = match spec.channels {
+        1 => opus::Channels::Mono,
+        2 => opus::Channels::Stereo,
+        n => {
+            return Err(anyhow!(
+                "opus output supports mono or stereo, got {} channels",
+                n
+            ))
+        }
+    };
+    if !matches!(spec.sample_rate, 8000 | 12000 | 16000 | 24000 | 48000) {
+        return Err(anyhow!(
+            "opus requires a capture rate of 8, 12, 16, 24, or 48 kHz, got {} Hz",
+            spec.sample_rate
+        ));
+    }
+    let mut encoder = opus::Encoder::new(spec.sample_rate, channels, opus::Application::Audio)
+        .map_err(|err| anyhow!("creating opus encoder failed: {}", err))?;
+    encoder
+        .set_bitrate(opus::Bitrate::Bits(bitrate as i32))
+        .map_err(|err| anyhow!("setting opus bitrate to {} failed: {}", bitrate, err))?;
+    let file = BufWriter::new(File::create(&path)?);
+    let channels = spec.channels as usize;
+    let sample_rate = spec.sample_rate;
+    let handle = thread::spawn(move || run_worker(rx, file, encoder, channels, sample_rate));
+    Ok(OpusWorker { handle })
+}
+
+fn run_worker(
+    rx: Receiver<Vec<f32>>,
+    file: BufWriter<File>,
+    mut encoder: opus::Encoder,
+    channels: usize,
+    sample_rate: u32,
+) -> Result<u64, Error> {
+    let mut writer = PacketWriter::new(file);
+    // Arbitrary but fixed logical stream serial; one logical stream per
+    // file, so collisions cannot occur.
+    let serial = 0x4f505553;
+    // The decoder must skip the encoder's lookahead, declared in the
+    // header in 48 kHz samples.
+    let lookahead = encoder.get_lookahead().unwrap_or(0) as u64;
+    let pre_skip = lookahead * 48000 / sample_rate as u64;
+    writer.write_packet(
+        opus_head(channels as u8, pre_skip as u16, sample_rate),
+        serial,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+    writer.write_packet(opus_tags(), serial, PacketWriteEndInfo::EndPage, 0)?;
+    let frame_samples = (sample_rate / FRAME_FRACTION) as usize * channels;
+    let mut pending: Vec<f32> = Vec::with_capacity(frame_samples);
+    let mut granule = pre_skip;
+    let mut samples_written = 0u64;
+    for buffer in rx {
+        samples_written += buffer.len() as u64;
+        pending.extend(buffer);
+        while pending.len() >= frame_samples {
+            let rest = pending.split_off(frame_samples);
+            let packet = encoder
+                .encode_vec_float(&pending, MAX_PACKET_BYTES)
+                .map_err(|err| anyhow!("opus encoding failed: {}", err))?;
+            granule += GRANULE_PER_FRAME;
+            writer.write_packet(packet, serial, PacketWriteEndInfo::NormalPacket, granule)?;
+            pending = rest;
+        }
+    }
+    // Opus frames are fixed-length, so the tail is padded with silence;
+    // the final granule position trims the padding at decode time.
+    let tail_frames = pending.len() as u64 / channels as u64;
+    pending.resize(frame_samples, 0.0);
+    let packet = encoder
+        .encode_vec_float(&pending, MAX_PACKET_BYTES)
+        .map_err(|err| anyhow!("opus encoding failed: {}", err))?;
+    granule += tail_frames * 48000 / sample_rate as u64;
+    writer.write_packet(packet, serial, PacketWriteEndInfo::EndStream, granule)?;
+    Ok(samples_written)
+}
+
+/// Identification header (RFC 7845 section 5.1): version, channel count,
+/// pre-skip, input rate, zero output gain, and the trivial channel
+/// mapping that covers mono and stereo.
+fn opus_head(channels: u8, pre_skip: u16, sample_rate: u32) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1);
+    head.push(channels);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&sample_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    head
+}
+
+/// Comment header (RFC 7845 section 5.2) with no user comments.
+fn opus_tags() -> Vec<u8> {
+    let vendor = env!("CARGO_PKG_NAME").as_bytes();
+    let mut tags = Vec::with_capacity(16 + vendor.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
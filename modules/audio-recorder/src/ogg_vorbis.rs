@@ -0,0 +1,108 @@
+//! Ogg Vorbis encoding worker, for distribution copies where lossless is
+//! not required but wav files are too bulky to share. Quality-targeted
+//! VBR keeps the perceptual level consistent across quiet and busy
+//! recordings instead of pinning a bitrate. Like the FLAC and Opus
+//! workers, the audio callback hands processed buffers over a bounded
+//! channel and the encoder runs on its own thread, never inline with
+//! capture.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::num::{NonZeroU32, NonZeroU8};
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{anyhow, Error};
+use hound::WavSpec;
+use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
+
+/// How many callback buffers may queue up before the callback drops audio
+/// instead of blocking.
+pub(crate) const QUEUE_DEPTH: usize = 64;
+
+/// A running Ogg Vorbis encoder thread, joined the same way as the FLAC
+/// worker: drop every sender, then call `finish`.
+pub(crate) struct VorbisWorker {
+    handle: JoinHandle<Result<u64, Error>>,
+}
+
+impl VorbisWorker {
+    /// Waits for the encoder to drain its queue and close the Ogg stream.
+    /// Returns the number of samples encoded, summed over channels. Call
+    /// only after the capture stream has been dropped.
+    pub(crate) fn finish(self) -> Result<u64, Error> {
+        self.handle
+            .join()
+            .map_err(|_| anyhow!("vorbis encoder thread panicked"))?
+    }
+}
+
+/// Spawns the encoder thread for one output file. Unlike Opus, Vorbis
+/// takes any sample rate and channel count the capture side produces, so
+/// only the quality factor needs validating.
+pub(crate) fn spawn_worker(
+    rx: Receiver<Vec<f32>>,
+    path: String,
+    spec: &WavSpec,
+    quality: f32,
+) -> Result<VorbisWorker, Error> {
+    if !(-0.2..=1.0).contains(&quality) {
+        return Err(anyhow!(
+            "vorbis quality must lie in -0.2..=1.0, got {}",
+            quality
+        ));
+    }
+    let sample_rate = NonZeroU32::new(spec.sample_rate)
+        .ok_or_else(|| anyhow!("vorbis output requires a nonzero sample rate"))?;
+    let channels = u8::try_from(spec.channels)
+        .ok()
+        .and_then(NonZeroU8::new)
+        .ok_or_else(|| {
+            anyhow!(
+                "vorbis output supports 1 to 255 channels, got {}",
+                spec.channels
+            )
+        })?;
+    let file = BufWriter::new(File::create(&path)?);
+    let channels_count = spec.channels as usize;
+    // The encoder holds raw pointers into libvorbis state and is not
+    // Send, so it is built on the worker thread; only the open file
+    // crosses over.
+    let handle =
+        thread::spawn(move || run_worker(rx, file, sample_rate, channels, quality, channels_count));
+    Ok(VorbisWorker { handle })
+}
+
+fn run_worker(
+    rx: Receiver<Vec<f32>>,
+    file: BufWriter<File>,
+    sample_rate: NonZeroU32,
+    channels_nz: NonZeroU8,
+    quality: f32,
+    channels: usize,
+) -> Result<u64, Error> {
+    let mut encoder = VorbisEncoderBuilder::new(sample_rate, channels_nz, file)?
+        .bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr {
+            target_quality: quality,
+        })
+        .build()?;
+    let mut samples_written = 0u64;
+    let mut planar: Vec<Vec<f32>> = vec![Vec::new(); channels];
+    for buffer in rx {
+        samples_written += buffer.len() as u64;
+        // The encoder takes planar blocks; deinterleave each buffer into
+        // reused per-channel planes. Capture buffers hold whole frames,
+        // so nothing carries over between blocks.
+        for plane in &mut planar {
+            plane.clear();
+        }
+        for frame in buffer.chunks_exact(channels) {
+            for (plane, &sample) in planar.iter_mut().zip(frame) {
+                plane.push(sample);
+            }
+        }
+        encoder.encode_audio_block(&planar)?;
+    }
+    encoder.finish()?;
+    Ok(samples_written)
+}
@@ -0,0 +1,133 @@
+//! Headerless raw PCM output, for downstream tools that expect bare
+//! sample data with a layout agreed out of band. The layout mirrors the
+//! wav data chunk: frames are channel-interleaved in capture order, and
+//! each sample is written at the same width the wav spec would use —
+//! 16-bit or 24-bit (3 bytes, two's complement) integers, or 32-bit IEEE
+//! floats — with the configured byte order. The sidecar records all of
+//! these parameters, so a raw file never has to be guessed at.
+//!
+//! Like the FLAC and Opus workers, the audio callback hands processed
+//! buffers over a bounded channel and the file is written on its own
+//! thread.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{anyhow, Error};
+use hound::WavSpec;
+
+/// How many callback buffers may queue up before the callback drops audio
+/// instead of blocking.
+pub(crate) const QUEUE_DEPTH: usize = 64;
+
+/// Byte order of the samples in a raw PCM file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    /// The label written into the sidecar.
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Endianness::Little => "little",
+            Endianness::Big => "big",
+        }
+    }
+}
+
+/// A running raw PCM writer thread, joined the same way as the encoder
+/// workers: drop every sender, then call `finish`.
+pub(crate) struct RawWorker {
+    handle: JoinHandle<Result<u64, Error>>,
+}
+
+impl RawWorker {
+    /// Waits for the writer to drain its queue and flush the file.
+    /// Returns the number of samples written, summed over channels. Call
+    /// only after the capture stream has been dropped.
+    pub(crate) fn finish(self) -> Result<u64, Error> {
+        self.handle
+            .join()
+            .map_err(|_| anyhow!("raw pcm writer thread panicked"))?
+    }
+}
+
+/// Spawns the writer thread for one output file. The spec decides the
+/// sample width exactly as it would for a wav file.
+pub(crate) fn spawn_worker(
+    rx: Receiver<Vec<f32>>,
+    path: String,
+    spec: &WavSpec,
+    endianness: Endianness,
+) -> Result<RawWorker, Error> {
+    let file = BufWriter::new(File::create(&path)?);
+    let spec = *spec;
+    let handle = thread::spawn(move || run_worker(rx, file, spec, endianness));
+    Ok(RawWorker { handle })
+}
+
+fn run_worker(
+    rx: Receiver<Vec<f32>>,
+    mut file: BufWriter<File>,
+    spec: WavSpec,
+    endianness: Endianness,
+) -> Result<u64, Error> {
+    let mut samples_written = 0u64;
+    let mut bytes = Vec::new();
+    for buffer in rx {
+        samples_written += buffer.len() as u64;
+        bytes.clear();
+        for sample in buffer {
+            encode_sample(sample, &spec, endianness, &mut bytes);
+        }
+        file.write_all(&bytes)?;
+    }
+    file.flush()?;
+    Ok(samples_written)
+}
+
+/// Appends one sample in the spec's width and the requested byte order.
+/// 24-bit samples are packed into 3 bytes rather than padded to 4, which
+/// is what headerless-PCM consumers conventionally expect.
+fn encode_sample(sample: f32, spec: &WavSpec, endianness: Endianness, out: &mut Vec<u8>) {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, _) => match endianness {
+            Endianness::Little => out.extend_from_slice(&sample.to_le_bytes()),
+            Endianness::Big => out.extend_from_slice(&sample.to_be_bytes()),
+        },
+        (hound::SampleFormat::Int, 32) => {
+            let value = (f64::from(sample.clamp(-1.0, 1.0)) * f64::from(i32::MAX)) as i32;
+            match endianness {
+                Endianness::Little => out.extend_from_slice(&value.to_le_bytes()),
+                Endianness::Big => out.extend_from_slice(&value.to_be_bytes()),
+            }
+        }
+        (hound::SampleFormat::Int, 24) => {
+            let value = (f64::from(sample.clamp(-1.0, 1.0)) * f64::from((1 << 23) - 1)) as i32;
+            let quad = match endianness {
+                Endianness::Little => value.to_le_bytes(),
+                Endianness::Big => value.to_be_bytes(),
+            };
+            match endianness {
+                Endianness::Little => out.extend_from_slice(&quad[..3]),
+                Endianness::Big => out.extend_from_slice(&quad[1..]),
+            }
+        }
+        // 8-bit mirrors the wav payload: unsigned, midpoint at 0x80.
+        (hound::SampleFormat::Int, 8) => {
+            let value = (f64::from(sample.clamp(-1.0, 1.0)) * f64::from(i8::MAX)) as i8;
+            out.push((value as u8).wrapping_add(0x80));
+        }
+        (hound::SampleFormat::Int, _) => {
+            let value = (f64::from(sample.clamp(-1.0, 1.0)) * f64::from(i16::MAX)) as i16;
+            match endianness {
+                Endianness::Little => out.extend_from_slice(&value.to_le_bytes()),
+                Endianness::Big => out.extend_from_slice(&value.to_be_bytes()),
+            }
+        }
+    }
+}
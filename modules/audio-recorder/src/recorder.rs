@@ -0,0 +1,4950 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{
+    Device, FromSample, HostId, Sample, SampleFormat, SampleRate, SizedSample, Stream,
+    StreamConfig, SupportedStreamConfig,
+};
+use hound::{WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+
+use crate::chunks;
+use crate::config::RecorderConfig;
+use crate::error::RecorderError;
+use crate::flac;
+use crate::getters::{
+    get_default_config, get_device, get_device_from_candidates, get_device_preferring, get_host,
+    get_user_config_with_periods,
+};
+use crate::decimate::Decimator;
+use crate::highpass::HighPass;
+use crate::spectrogram::{self, SpectrogramConfig};
+use crate::interrupt::{InterruptHandles, StopHandle};
+use crate::normalize;
+use crate::ogg_opus;
+use crate::ogg_vorbis;
+use crate::raw_pcm::{self, Endianness};
+use crate::resample;
+use crate::spectrum::{self, WindowType};
+
+/// Anything a wav file can be written into: a buffered file on disk in
+/// normal operation, or e.g. an in-memory cursor for tests and the
+/// streaming integrations. Sinks must also be readable, since append
+/// mode re-parses the existing header through the same handle.
+/// Blanket-implemented, never implemented by hand.
+pub trait WavSink: Read + Write + Seek + Send {}
+
+impl<T: Read + Write + Seek + Send> WavSink for T {}
+
+/// Buffered file sink. `BufWriter` alone does not satisfy [`WavSink`]'s
+/// read bound; reads flush the write buffer first so they observe every
+/// byte already written.
+struct BufferedFile(BufWriter<File>);
+
+impl Write for BufferedFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Seek for BufferedFile {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl Read for BufferedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.flush()?;
+        self.0.get_mut().read(buf)
+    }
+}
+
+pub type WriteHandle = Arc<Mutex<Option<WavWriter<Box<dyn WavSink>>>>>;
+
+/// One mono writer per output channel when channel splitting is enabled.
+type SplitWriteHandle = Arc<Mutex<Vec<WavWriter<BufWriter<File>>>>>;
+
+/// Shared user callback for cpal stream errors.
+type ErrorCallback = Arc<dyn Fn(&cpal::StreamError) + Send + Sync>;
+
+/// Embedder callback receiving every captured buffer, with the stream
+/// config it was captured under.
+type BufferCallback = Arc<dyn Fn(&[f32], &StreamConfig) + Send + Sync>;
+
+/// Largest wav data payload before the format's 32-bit size fields overflow,
+/// with a margin left for the headers and buffers still in flight.
+const MAX_WAV_BYTES: u64 = u32::MAX as u64 - 16 * 1024 * 1024;
+
+/// How often the control loop checks the size of the file being written.
+pub(crate) const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the triggered mode polls the input level. Short enough that
+/// event onsets are not missed by a whole buffer of hangover.
+const TRIGGER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a scheduled wait re-reads the wall clock, so a system time
+/// update (e.g. an RTC or NTP sync) moves the start accordingly.
+const CLOCK_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Normalized level at or above which a sample is counted as clipped. Just
+/// under full scale so integer formats that never quite reach 1.0 register.
+const CLIP_THRESHOLD: f32 = 0.999;
+
+/// Fraction of clipped samples above which a warning is printed at stop.
+const CLIP_WARN_FRACTION: f64 = 0.001;
+
+/// First wait before retrying to re-acquire a lost device.
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait between reconnect attempts; brown-outs usually resolve in
+/// seconds, but a physically unplugged device may stay gone for hours.
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How many level readings may queue up for the metering callback before
+/// new ones are dropped instead of blocking the audio thread.
+const LEVEL_QUEUE_DEPTH: usize = 16;
+
+/// How many callback buffers may queue for the TCP streaming thread
+/// before frames are dropped instead of blocking the audio callback. A
+/// stalled network link costs audio on the wire, never in the file.
+const TCP_QUEUE_DEPTH: usize = 64;
+
+/// Queue depth for the loudness worker; a dropped buffer only nudges the
+/// per-file figure, so the queue is kept short.
+const LOUDNESS_QUEUE_DEPTH: usize = 64;
+
+/// Queue depth for the balance worker, sized like the loudness queue; a
+/// dropped buffer barely moves the per-file statistics.
+const BALANCE_QUEUE_DEPTH: usize = 64;
+
+/// Correlation between the first two channels below which the finalize
+/// log warns about a possible wiring fault. Two elements of one array
+/// hearing the same field should correlate well above this.
+const BALANCE_MIN_CORRELATION: f32 = 0.2;
+
+/// RMS level difference between the first two channels, in dB, beyond
+/// which the finalize log warns about a dead or padded element.
+const BALANCE_MAX_IMBALANCE_DB: f32 = 6.0;
+
+/// How many buffer timestamps may queue for the timeline writer thread.
+/// A full queue loses entries rather than blocking the audio thread; the
+/// surviving entries still bracket any stall tightly.
+const TIMELINE_QUEUE_DEPTH: usize = 64;
+
+/// Standard capture rates tried highest-first when a stream fails to
+/// open and a downgrade is allowed (see
+/// [`Recorder::set_allow_downgrade`]).
+const DOWNGRADE_RATES: &[u32] = &[
+    192_000, 96_000, 48_000, 44_100, 32_000, 22_050, 16_000, 8_000,
+];
+
+/// Seconds of audio the lock-free ring can buffer between the audio
+/// callback and the writer thread before samples are dropped.
+const RING_CAPACITY_SECS: usize = 4;
+
+/// How long the ring writer thread sleeps when the ring is empty.
+const RING_POLL: Duration = Duration::from_millis(1);
+
+/// How many samples the ring writer thread drains per writer-lock
+/// acquisition by default. Large enough that the per-write overhead
+/// amortizes away, small enough that a finalize waiting on the lock gets
+/// it within a few milliseconds of audio.
+const DEFAULT_WRITE_CHUNK_SAMPLES: usize = 4096;
+
+/// Default Opus bitrate in bits per second. Enough for intelligible
+/// monitoring of a mono hydrophone channel over a cellular link.
+const DEFAULT_OPUS_BITRATE: u32 = 32_000;
+
+/// Amplitude of the injected calibration tone: -6 dBFS, comfortably clear
+/// of both the noise floor and full scale so the reference level survives
+/// any later gain staging without clipping.
+const TEST_TONE_AMPLITUDE: f32 = 0.5;
+
+/// Extensions the recorder writes audio under, one per output format.
+/// Retention only ever deletes files with one of these extensions, so
+/// sidecars, the batch-state file, and other tenants of the output
+/// directory are safe from it.
+const RECORDING_EXTENSIONS: &[&str] = &["wav", "flac", "opus", "ogg", "raw"];
+
+/// Per-channel input levels computed from one callback buffer, with full
+/// scale at 1.0.
+#[derive(Clone, Debug)]
+pub struct LevelInfo {
+    pub rms: Vec<f32>,
+    pub peak: Vec<f32>,
+}
+
+/// Input level statistics gathered over a recording run.
+#[derive(Clone, Copy, Debug)]
+pub struct ClipStats {
+    /// Highest absolute input level seen, with full scale at 1.0.
+    pub peak: f32,
+    /// Number of samples at or above the clipping threshold.
+    pub clipped_samples: u64,
+}
+
+/// Lifecycle events emitted as output files open and close, for embedders
+/// that track recordings in a dashboard or upload finished files.
+#[derive(Clone, Debug)]
+pub enum RecorderEvent {
+    FileStarted {
+        path: PathBuf,
+        timestamp: DateTime<Local>,
+    },
+    FileStopped {
+        path: PathBuf,
+        samples_written: u64,
+        duration: Duration,
+        /// Hex SHA-256 of the finalized file, when checksums are enabled.
+        checksum: Option<String>,
+    },
+}
+
+/// How aggressively buffered audio is pushed to stable storage. Buffered
+/// writes are fastest but a power failure can lose everything the OS has
+/// not yet flushed, which on a flaky SD card is worth trading some
+/// throughput to bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurabilityMode {
+    /// OS-buffered writes only; the default, matching earlier behavior.
+    Buffered,
+    /// Flush the writer and fsync the file every `every_secs` seconds,
+    /// so a power failure loses at most that much audio. Combine with
+    /// [`Recorder::set_flush_interval_secs`] to also keep the header
+    /// parsable after a crash.
+    Fsync { every_secs: u64 },
+}
+
+/// Which of this recorder's old files may be deleted to keep a long-term
+/// station running once the disk fills: the classic circular-buffer
+/// logger. Enforced after each file finalizes; the file currently being
+/// written is never a candidate.
+#[derive(Clone, Copy, Debug)]
+pub enum RetentionPolicy {
+    /// Delete oldest files until the recorder's files total at most this
+    /// many bytes.
+    MaxTotalBytes(u64),
+    /// Delete files last modified longer ago than this.
+    MaxAge(Duration),
+}
+
+/// Progress of a budgeted batch run, persisted to the output directory
+/// when batch persistence is enabled so a brownout mid-experiment resumes
+/// toward the remaining file-count or time budget instead of starting
+/// over.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct BatchState {
+    /// Segments finished so far.
+    pub files: u64,
+    /// Wall-clock seconds of the time budget consumed so far.
+    pub elapsed_secs: u64,
+}
+
+/// End-of-session health counters, for the shutdown report that tells an
+/// operator whether an unattended run can be trusted.
+#[derive(Clone, Copy, Debug)]
+pub struct SessionSummary {
+    /// Files opened since init, including roll-overs.
+    pub files: u64,
+    /// Samples captured across all channels.
+    pub samples: u64,
+    /// Cumulative time the capture stream was running.
+    pub duration: Duration,
+    pub dropped_samples: u64,
+    pub reconnects: u32,
+    /// Highest absolute input level seen, with full scale at 1.0.
+    pub peak: f32,
+}
+
+/// Coarse recorder lifecycle state, readable from any thread. Stored as a
+/// `u8` in an atomic so the control loop can publish transitions without
+/// a lock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecorderState {
+    Idle = 0,
+    Recording = 1,
+    Paused = 2,
+    Finalizing = 3,
+}
+
+/// Which clock reading goes into filenames and metadata chunks. Local
+/// time reads naturally on a single station; UTC is what multi-site
+/// deployments need to correlate recordings across time zones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeZoneMode {
+    Local,
+    Utc,
+}
+
+/// On-disk format for recorded files. FLAC roughly halves storage on
+/// hydrophone signals at no quality cost, which matters on SD cards;
+/// Ogg-Opus is lossy and meant for low-bitrate telemetry uploads, not
+/// archival; Ogg Vorbis is likewise lossy but tuned by a perceptual
+/// quality factor in `-0.2..=1.0`, sized for distribution copies rather
+/// than telemetry; raw PCM is the wav data payload without the
+/// container, for downstream tools that want bare samples (see
+/// [`crate::raw_pcm`] for the exact layout); wav remains the default for
+/// maximal tool compatibility.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Wav,
+    Flac,
+    Opus,
+    OggVorbis { quality: f32 },
+    RawPcm { endianness: Endianness },
+}
+
+/// A cue marker queued for the file currently being written.
+struct Marker {
+    /// Frame offset into the file's data chunk.
+    offset: u64,
+    label: String,
+}
+
+/// Deployment position embedded into recorded files.
+#[derive(Clone, Copy, Debug)]
+pub struct Location {
+    pub lat: f64,
+    pub lon: f64,
+    pub depth_m: Option<f32>,
+}
+
+/// Per-file agreement between the first two capture channels, for
+/// catching a dead or miswired array element (see
+/// [`Recorder::set_balance_check`]).
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelBalance {
+    /// Pearson correlation coefficient between the channels, in -1..=1.
+    pub correlation: f32,
+    /// RMS level of the first channel relative to the second, in dB;
+    /// positive means the first channel is louder.
+    pub level_diff_db: f32,
+}
+
+/// Running sums the balance worker accumulates per file, enough to
+/// derive the correlation coefficient and level ratio at finalize.
+#[derive(Default)]
+struct BalanceAccum {
+    sum_a: f64,
+    sum_b: f64,
+    sum_aa: f64,
+    sum_bb: f64,
+    sum_ab: f64,
+    frames: u64,
+}
+
+/// Shared TPDF dither source for bit-depth reduction. Triangular noise of
+/// one output LSB peak amplitude is added before truncation, decorrelating
+/// the quantization error from the signal so quiet passages gain a flat
+/// noise floor instead of harmonic distortion. The noise comes from a
+/// xorshift32 generator stepped through a relaxed atomic — racing
+/// callbacks could at worst reuse a noise value, which dither does not
+/// care about — so the source can live in the cloneable context.
+struct Dither {
+    /// One least significant bit of the output depth, in normalized
+    /// full-scale units.
+    lsb: f32,
+    state: AtomicU32,
+}
+
+impl Dither {
+    fn new(output_bits: u16) -> Self {
+        Self {
+            lsb: 1.0 / (1u32 << (output_bits - 1)) as f32,
+            state: AtomicU32::new(0x9e37_79b9),
+        }
+    }
+
+    /// One uniform sample in [0, 1).
+    fn next_uniform(&self) -> f32 {
+        let mut x = self.state.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state.store(x, Ordering::Relaxed);
+        x as f32 / u32::MAX as f32
+    }
+
+    /// Adds one TPDF noise sample, keeping the result in range for the
+    /// integer conversion that follows.
+    fn apply(&self, sample: f32) -> f32 {
+        let noise = (self.next_uniform() - self.next_uniform()) * self.lsb;
+        (sample + noise).clamp(-1.0, 1.0)
+    }
+}
+
+/// Phase-continuous generator for the calibration tone injected at the
+/// start of a recording. The callback substitutes generated samples for
+/// live input until `remaining` runs out, so the boundary falls on an
+/// exact, known sample position.
+struct ToneState {
+    freq_hz: f32,
+    sample_rate: u32,
+    channels: u16,
+    /// Interleaved samples still to substitute.
+    remaining: u64,
+    frame: u64,
+    channel: u16,
+}
+
+impl ToneState {
+    fn new(freq_hz: f32, secs: u64, sample_rate: u32, channels: u16) -> Self {
+        ToneState {
+            freq_hz,
+            sample_rate,
+            channels,
+            remaining: secs * u64::from(sample_rate) * u64::from(channels),
+            frame: 0,
+            channel: 0,
+        }
+    }
+
+    /// Produces the next interleaved sample. Every channel of a frame
+    /// carries the same value, so all channels share one reference level.
+    fn next_sample(&mut self) -> f32 {
+        let t = self.frame as f32 / self.sample_rate as f32;
+        let value = TEST_TONE_AMPLITUDE * (std::f32::consts::TAU * self.freq_hz * t).sin();
+        self.channel += 1;
+        if self.channel == self.channels {
+            self.channel = 0;
+            self.frame += 1;
+        }
+        self.remaining -= 1;
+        value
+    }
+}
+
+/// Arrival time of one captured buffer, taken on the audio thread and
+/// resolved into timeline rows on the writer thread.
+struct TimelineEntry {
+    /// Session total of interleaved samples when the buffer arrived; the
+    /// writer thread turns it into an offset into the current file.
+    samples: u64,
+    mono: Instant,
+    wall: SystemTime,
+}
+
+/// Shared state handed to the cpal input callbacks.
+#[derive(Clone)]
+struct CallbackContext {
+    writer: WriteHandle,
+    dropped: Arc<AtomicU64>,
+    write_errors: Arc<AtomicU64>,
+    samples_written: Arc<AtomicU64>,
+    peak: Arc<AtomicU32>,
+    pretrigger: Arc<Mutex<VecDeque<f32>>>,
+    gain: Arc<AtomicU32>,
+    channel_gains: Option<Arc<Vec<f32>>>,
+    gain_clipped: Arc<AtomicBool>,
+    soft_clip: bool,
+    dither: Option<Arc<Dither>>,
+    session_peak: Arc<AtomicU32>,
+    clipped_samples: Arc<AtomicU64>,
+    total_samples: Arc<AtomicU64>,
+    channels: u16,
+    downmix: bool,
+    selection: Option<Vec<u16>>,
+    split_writers: SplitWriteHandle,
+    split: bool,
+    highpass: Option<Arc<Mutex<HighPass>>>,
+    decimate: Option<Arc<Mutex<Decimator>>>,
+    tone: Option<Arc<Mutex<ToneState>>>,
+    level_tx: Option<SyncSender<LevelInfo>>,
+    resample_tx: Option<SyncSender<Vec<f32>>>,
+    encoder_tx: Option<SyncSender<Vec<f32>>>,
+    spectrum_tx: Option<SyncSender<Vec<f32>>>,
+    tcp_tx: Option<SyncSender<Vec<f32>>>,
+    tcp_dropped: Arc<AtomicU64>,
+    loudness_tx: Option<SyncSender<Vec<f32>>>,
+    balance_tx: Option<SyncSender<Vec<f32>>>,
+    timeline_tx: Option<SyncSender<TimelineEntry>>,
+    buffer_cb: Option<(BufferCallback, StreamConfig)>,
+    // The producer is single-consumer by construction (only the audio
+    // callback thread pushes); the mutex exists solely so the context
+    // stays cloneable and never sees contention.
+    ring: Option<Arc<Mutex<rtrb::Producer<f32>>>>,
+}
+
+pub struct Recorder {
+    writer: WriteHandle,
+    interrupt_handles: InterruptHandles,
+    default_config: SupportedStreamConfig,
+    user_config: StreamConfig,
+    device: Device,
+    name: String,
+    path: PathBuf,
+    pending_path: Option<PathBuf>,
+    current_file: String,
+    dropped_samples: Arc<AtomicU64>,
+    write_errors: Arc<AtomicU64>,
+    samples_written: Arc<AtomicU64>,
+    peak_level: Arc<AtomicU32>,
+    pretrigger: Arc<Mutex<VecDeque<f32>>>,
+    gain: Arc<AtomicU32>,
+    channel_gains: Option<Arc<Vec<f32>>>,
+    gain_clipped: Arc<AtomicBool>,
+    soft_clip: bool,
+    dither: bool,
+    session_peak: Arc<AtomicU32>,
+    clipped_samples: Arc<AtomicU64>,
+    total_samples: Arc<AtomicU64>,
+    level_tx: Option<SyncSender<LevelInfo>>,
+    spectrum_tx: Option<SyncSender<Vec<f32>>>,
+    tcp_tx: Option<SyncSender<Vec<f32>>>,
+    tcp_dropped: Arc<AtomicU64>,
+    loudness_tx: Option<SyncSender<Vec<f32>>>,
+    loudness_accum: Option<Arc<Mutex<(f64, u64)>>>,
+    balance_tx: Option<SyncSender<Vec<f32>>>,
+    balance_accum: Option<Arc<Mutex<BalanceAccum>>>,
+    last_balance: Option<ChannelBalance>,
+    timeline_tx: Option<SyncSender<TimelineEntry>>,
+    timeline_file: Arc<Mutex<Option<BufWriter<File>>>>,
+    timeline_base: Arc<AtomicU64>,
+    buffer_cb: Option<BufferCallback>,
+    last_rms_dbfs: Option<f32>,
+    lock_free: bool,
+    write_chunk_samples: usize,
+    ring_thread: Option<thread::JoinHandle<()>>,
+    event_callback: Option<Box<dyn Fn(RecorderEvent) + Send>>,
+    error_callback: Option<ErrorCallback>,
+    description: Option<String>,
+    location: Option<Location>,
+    downmix: bool,
+    channel_selection: Option<Vec<u16>>,
+    split_channels: bool,
+    split_writers: SplitWriteHandle,
+    highpass_hz: Option<f32>,
+    decimation: Option<u32>,
+    test_tone: Option<(f32, u64)>,
+    target_sample_rate: Option<u32>,
+    format: OutputFormat,
+    flac_worker: Option<flac::FlacWorker>,
+    opus_worker: Option<ogg_opus::OpusWorker>,
+    vorbis_worker: Option<ogg_vorbis::VorbisWorker>,
+    raw_worker: Option<raw_pcm::RawWorker>,
+    opus_bitrate: u32,
+    encoder_tx: Option<SyncSender<Vec<f32>>>,
+    markers: Mutex<Vec<Marker>>,
+    info_tags: Vec<([u8; 4], String)>,
+    min_free_bytes: Option<u64>,
+    max_file_bytes: Option<u64>,
+    retention: Option<RetentionPolicy>,
+    persist_batch: bool,
+    memory_sink: bool,
+    active_window: Option<(NaiveTime, NaiveTime)>,
+    sidecar: bool,
+    checksum: bool,
+    spectrogram: Option<SpectrogramConfig>,
+    flush_interval: Option<Duration>,
+    last_flush: Instant,
+    durability: DurabilityMode,
+    sync_file: Option<File>,
+    last_sync: Instant,
+    low_disk: bool,
+    file_started: Option<DateTime<Local>>,
+    filename_template: Option<String>,
+    file_index: u64,
+    date_folders: bool,
+    min_event_secs: Option<u64>,
+    catalog_csv: bool,
+    normalize_peak: Option<f32>,
+    append: bool,
+    timezone: TimeZoneMode,
+    min_clock_year: Option<i32>,
+    clock_fallback: Option<DateTime<Local>>,
+    clock_offset: Option<chrono::Duration>,
+    clock_untrusted: bool,
+    host_id: HostId,
+    device_name: Option<String>,
+    device_lost: Arc<AtomicBool>,
+    state: Arc<AtomicU8>,
+    auto_reconnect: bool,
+    allow_downgrade: bool,
+    reconnects: Arc<AtomicU32>,
+    stream_timeout: Option<Duration>,
+    watchdog_samples: u64,
+    watchdog_seen: Instant,
+    rate_started: Option<Instant>,
+    rate_samples_start: u64,
+    measured_rate: Option<f64>,
+    recorded: Duration,
+    stream: Option<Stream>,
+}
+
+/// Builds a [`Recorder`] from chained setters, with the same defaults as
+/// the command line, so call sites only spell out what differs and new
+/// options do not keep growing the `init` signature.
+pub struct RecorderBuilder {
+    name: String,
+    path: PathBuf,
+    host: HostId,
+    sample_rate: u32,
+    channels: u16,
+    buffer_size: u32,
+    periods: Option<u32>,
+    device: Option<String>,
+    device_candidates: Vec<String>,
+    prefer_device: Option<String>,
+    device_timeout: Option<Duration>,
+    interrupts: Option<InterruptHandles>,
+}
+
+impl Default for RecorderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecorderBuilder {
+    pub fn new() -> Self {
+        Self {
+            name: "audio".to_string(),
+            path: PathBuf::from("/output/audio"),
+            host: cpal::default_host().id(),
+            sample_rate: 44100,
+            channels: 2,
+            buffer_size: 1024,
+            periods: None,
+            device: None,
+            device_candidates: Vec::new(),
+            prefer_device: None,
+            device_timeout: None,
+            interrupts: None,
+        }
+    }
+
+    /// Base name for recorded files.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Directory the recorded files are written into.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn host(mut self, host: HostId) -> Self {
+        self.host = host;
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn channels(mut self, channels: u16) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Capture buffer size in frames.
+    pub fn buffer_size(mut self, buffer_size: u32) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Number of `buffer_size`-frame periods of headroom to ask the
+    /// backend for, for tuning underruns against latency independently of
+    /// the period length. Backends without explicit period support get a
+    /// correspondingly larger buffer, with a warning logged.
+    pub fn buffer_periods(mut self, periods: u32) -> Self {
+        self.periods = Some(periods);
+        self
+    }
+
+    /// Input device name as shown by `--list-devices`; the host default
+    /// when not set.
+    pub fn device(mut self, name: impl Into<String>) -> Self {
+        self.device = Some(name.into());
+        self
+    }
+
+    /// Ordered list of candidate device names; the first one present is
+    /// used, so a backup interface takes over when the primary fails to
+    /// enumerate. Overrides [`Self::device`] when non-empty.
+    pub fn device_candidates(mut self, names: Vec<String>) -> Self {
+        self.device_candidates = names;
+        self
+    }
+
+    /// Substring auto-selection should prefer when no device name is set,
+    /// e.g. `"USB"` to skip a Pi's onboard HDMI and headphone inputs.
+    /// Ignored when [`Self::device`] or [`Self::device_candidates`] names
+    /// a device explicitly. When nothing matches, the first non-onboard
+    /// input — or, failing that, the host default — is used with a
+    /// warning.
+    pub fn prefer_device_substring(mut self, substring: impl Into<String>) -> Self {
+        self.prefer_device = Some(substring.into());
+        self
+    }
+
+    /// Bounds how long device acquisition in [`Self::build`] may block.
+    /// Some USB interfaces block indefinitely in the backend when another
+    /// process — or a crashed previous instance — still holds the device;
+    /// with a timeout, `build` fails with
+    /// [`RecorderError::Timeout`](crate::error::RecorderError::Timeout)
+    /// instead of hanging, so a supervisor can restart cleanly.
+    pub fn device_timeout_secs(mut self, secs: u64) -> Self {
+        self.device_timeout = Some(Duration::from_secs(secs));
+        self
+    }
+
+    /// Shares an existing interrupt handler instead of installing a new
+    /// one. The process-wide signal handler can only be installed once, so
+    /// every recorder after the first in a process must share it.
+    pub fn interrupt_handles(mut self, handles: InterruptHandles) -> Self {
+        self.interrupts = Some(handles);
+        self
+    }
+
+    /// Validates the settings against the host and builds the recorder.
+    pub fn build(self) -> Result<Recorder, RecorderError> {
+        let (device, device_name, default_config) = match self.device_timeout {
+            Some(timeout) => acquire_device_with_timeout(
+                self.host,
+                self.device,
+                self.device_candidates,
+                self.prefer_device,
+                timeout,
+            )?,
+            None => acquire_device(
+                self.host,
+                self.device,
+                self.device_candidates,
+                self.prefer_device,
+            )?,
+        };
+        let user_config =
+            get_user_config_with_periods(
+            &device,
+            self.sample_rate,
+            self.channels,
+            self.buffer_size,
+            self.periods,
+        )?;
+        let interrupt_handles = match self.interrupts {
+            Some(handles) => handles,
+            None => InterruptHandles::new()?,
+        };
+        Ok(Recorder {
+            writer: Arc::new(Mutex::new(None)),
+            interrupt_handles,
+            default_config,
+            user_config,
+            device,
+            name: self.name,
+            path: self.path,
+            pending_path: None,
+            current_file: String::new(),
+            dropped_samples: Arc::new(AtomicU64::new(0)),
+            write_errors: Arc::new(AtomicU64::new(0)),
+            samples_written: Arc::new(AtomicU64::new(0)),
+            peak_level: Arc::new(AtomicU32::new(0)),
+            pretrigger: Arc::new(Mutex::new(VecDeque::new())),
+            gain: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            channel_gains: None,
+            gain_clipped: Arc::new(AtomicBool::new(false)),
+            soft_clip: false,
+            dither: false,
+            session_peak: Arc::new(AtomicU32::new(0)),
+            clipped_samples: Arc::new(AtomicU64::new(0)),
+            total_samples: Arc::new(AtomicU64::new(0)),
+            level_tx: None,
+            spectrum_tx: None,
+            tcp_tx: None,
+            tcp_dropped: Arc::new(AtomicU64::new(0)),
+            loudness_tx: None,
+            loudness_accum: None,
+            balance_tx: None,
+            balance_accum: None,
+            last_balance: None,
+            timeline_tx: None,
+            timeline_file: Arc::new(Mutex::new(None)),
+            timeline_base: Arc::new(AtomicU64::new(0)),
+            buffer_cb: None,
+            last_rms_dbfs: None,
+            lock_free: false,
+            write_chunk_samples: DEFAULT_WRITE_CHUNK_SAMPLES,
+            ring_thread: None,
+            event_callback: None,
+            error_callback: None,
+            description: None,
+            location: None,
+            downmix: false,
+            channel_selection: None,
+            split_channels: false,
+            split_writers: Arc::new(Mutex::new(Vec::new())),
+            highpass_hz: None,
+            decimation: None,
+            test_tone: None,
+            target_sample_rate: None,
+            format: OutputFormat::Wav,
+            flac_worker: None,
+            opus_worker: None,
+            vorbis_worker: None,
+            raw_worker: None,
+            opus_bitrate: DEFAULT_OPUS_BITRATE,
+            encoder_tx: None,
+            markers: Mutex::new(Vec::new()),
+            info_tags: Vec::new(),
+            min_free_bytes: None,
+            max_file_bytes: None,
+            retention: None,
+            persist_batch: false,
+            memory_sink: false,
+            active_window: None,
+            sidecar: false,
+            checksum: false,
+            spectrogram: None,
+            flush_interval: None,
+            last_flush: Instant::now(),
+            durability: DurabilityMode::Buffered,
+            sync_file: None,
+            last_sync: Instant::now(),
+            low_disk: false,
+            file_started: None,
+            filename_template: None,
+            file_index: 0,
+            date_folders: false,
+            min_event_secs: None,
+            catalog_csv: false,
+            normalize_peak: None,
+            append: false,
+            timezone: TimeZoneMode::Local,
+            min_clock_year: None,
+            clock_fallback: None,
+            clock_offset: None,
+            clock_untrusted: false,
+            host_id: self.host,
+            device_name,
+            device_lost: Arc::new(AtomicBool::new(false)),
+            state: Arc::new(AtomicU8::new(RecorderState::Idle as u8)),
+            auto_reconnect: false,
+            allow_downgrade: false,
+            reconnects: Arc::new(AtomicU32::new(0)),
+            stream_timeout: None,
+            watchdog_samples: 0,
+            watchdog_seen: Instant::now(),
+            rate_started: None,
+            rate_samples_start: 0,
+            measured_rate: None,
+            recorded: Duration::ZERO,
+            stream: None,
+        })
+    }
+}
+
+/// Resolves the configured capture device, the name to reconnect to, and
+/// the device's default config. Split out of [`RecorderBuilder::build`]
+/// so the same lookup can also run on a helper thread when a device
+/// timeout is set.
+fn acquire_device(
+    host_id: HostId,
+    device: Option<String>,
+    candidates: Vec<String>,
+    prefer: Option<String>,
+) -> Result<(Device, Option<String>, SupportedStreamConfig), Error> {
+    let host = get_host(host_id)?;
+    let resolved = if !candidates.is_empty() {
+        get_device_from_candidates(host, &candidates)?
+    } else if let (None, Some(preferred)) = (&device, &prefer) {
+        get_device_preferring(host, preferred)?
+    } else {
+        get_device(host, device.clone())?
+    };
+    // Remember the device actually chosen, so a reconnect after
+    // device loss targets it rather than the full candidate list.
+    let device_name = resolved.name().ok().or(device);
+    let default_config = get_default_config(&resolved)?;
+    Ok((resolved, device_name, default_config))
+}
+
+/// Runs [`acquire_device`] on a helper thread and gives up after
+/// `timeout`. Opening a device another process still holds can block in
+/// the backend with no feedback; erroring out lets a supervisor restart
+/// instead of hanging. A blocked open cannot be cancelled, so on timeout
+/// the helper thread is left to finish — and its result to be discarded —
+/// in the background.
+fn acquire_device_with_timeout(
+    host_id: HostId,
+    device: Option<String>,
+    candidates: Vec<String>,
+    prefer: Option<String>,
+    timeout: Duration,
+) -> Result<(Device, Option<String>, SupportedStreamConfig), Error> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(acquire_device(host_id, device, candidates, prefer));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(_) => Err(RecorderError::Timeout(timeout.as_secs()).into()),
+    }
+}
+
+impl Recorder {
+    /// Thin wrapper over [`RecorderBuilder`] kept for callers that predate
+    /// the builder.
+    pub fn init(
+        name: String,
+        path: PathBuf,
+        host: HostId,
+        sample_rate: u32,
+        channels: u16,
+        buffer_size: u32,
+        device_name: Option<String>,
+    ) -> Result<Self, RecorderError> {
+        let mut builder = RecorderBuilder::new()
+            .name(name)
+            .path(path)
+            .host(host)
+            .sample_rate(sample_rate)
+            .channels(channels)
+            .buffer_size(buffer_size);
+        if let Some(device) = device_name {
+            builder = builder.device(device);
+        }
+        builder.build()
+    }
+
+    /// Builds a recorder from a TOML configuration file on the default
+    /// host, using the same defaults as the command line for missing keys.
+    /// See [`RecorderConfig`](crate::config::RecorderConfig) for the
+    /// recognized keys.
+    pub fn from_config_file(path: &Path) -> Result<Self, Error> {
+        let config = RecorderConfig::load(path)?;
+        Ok(Self::init(
+            config.name,
+            config.path,
+            cpal::default_host().id(),
+            config.sample_rate,
+            config.channels,
+            config.buffer_size,
+            config.device,
+        )?)
+    }
+
+    /// Records until interrupted by Ctrl+C.
+    pub fn record(&mut self) -> Result<(), RecorderError> {
+        self.init_writer()?;
+        self.start_stream()?;
+        log::info!("REC: {}", self.current_file);
+        loop {
+            if self.interrupt_handles.stream_wait_timeout(SIZE_CHECK_INTERVAL) {
+                break;
+            }
+            if self.disk_low()? {
+                break;
+            }
+            if self.write_errors() > 0 {
+                break;
+            }
+            self.check_stream_health()?;
+            self.flush_if_due();
+            self.sync_if_due();
+            if self.writer_bytes() >= self.roll_bytes() {
+                self.roll_writer()?;
+            }
+        }
+        self.stop_stream();
+        self.finalize_writer()?;
+        self.report_dropped();
+        self.log_summary();
+        self.take_write_errors()?;
+        Ok(())
+    }
+
+    /// Records for `secs` seconds, or until interrupted by Ctrl+C.
+    pub fn record_secs(&mut self, secs: u64) -> Result<(), RecorderError> {
+        self.init_writer()?;
+        self.start_stream()?;
+        log::info!("REC: {}", self.current_file);
+        self.wait_until(Instant::now() + Duration::from_secs(secs))?;
+        self.stop_stream();
+        self.finalize_writer()?;
+        self.report_dropped();
+        self.take_write_errors()?;
+        Ok(())
+    }
+
+    /// Waits until the wall-clock `start` time, then records for `secs`
+    /// seconds. The wait blocks on the interrupt condvar rather than
+    /// spinning, and an interrupt during the wait cancels the recording.
+    pub fn record_at(&mut self, start: DateTime<Local>, secs: u64) -> Result<(), Error> {
+        while let Ok(remaining) = start.signed_duration_since(Local::now()).to_std() {
+            if self
+                .interrupt_handles
+                .stream_wait_timeout(remaining.min(CLOCK_RECHECK_INTERVAL))
+            {
+                return Ok(());
+            }
+        }
+        Ok(self.record_secs(secs)?)
+    }
+
+    /// Records until interrupted by Ctrl+C, finalizing the current file and
+    /// opening a new one every `split_secs` while the stream keeps running,
+    /// so long deployments produce bounded, individually timestamped files.
+    pub fn record_with_split(&mut self, split_secs: u64) -> Result<(), Error> {
+        if self.format != OutputFormat::Wav {
+            return Err(anyhow!("split recording only supports wav output"));
+        }
+        if self.split_channels {
+            return Err(anyhow!(
+                "split recording does not combine with per-channel files"
+            ));
+        }
+        self.init_writer()?;
+        self.start_stream()?;
+        log::info!("REC: {}", self.current_file);
+        loop {
+            let interrupted = self.wait_until(Instant::now() + Duration::from_secs(split_secs))?;
+            if interrupted {
+                break;
+            }
+            self.roll_writer()?;
+        }
+        self.stop_stream();
+        self.finalize_writer()?;
+        self.report_dropped();
+        self.take_write_errors()?;
+        Ok(())
+    }
+
+    /// Records `secs`-long files from one continuously running stream,
+    /// swapping the writer at each boundary instead of tearing the stream
+    /// down the way `batch_recording` does, so no samples are lost
+    /// between segments. Each boundary logs how long the swap held the
+    /// writer lock and how many samples the callback dropped meanwhile
+    /// (ideally zero), so continuity across segments can be verified.
+    /// Runs until interrupted or the disk fills; returns the paths of the
+    /// finalized files.
+    pub fn record_gapless(&mut self, secs: u64) -> Result<Vec<PathBuf>, Error> {
+        if self.format != OutputFormat::Wav {
+            return Err(anyhow!("gapless recording only supports wav output"));
+        }
+        if self.split_channels {
+            return Err(anyhow!(
+                "gapless recording does not combine with per-channel files"
+            ));
+        }
+        self.init_writer()?;
+        self.start_stream()?;
+        log::info!("REC: {}", self.current_file);
+        let mut files = Vec::new();
+        loop {
+            let interrupted = self.wait_until(Instant::now() + Duration::from_secs(secs))?;
+            files.extend(self.current_file());
+            if interrupted {
+                break;
+            }
+            let dropped_before = self.dropped_samples();
+            let swap_started = Instant::now();
+            self.roll_writer()?;
+            log::info!(
+                "gapless boundary: swap took {} µs, {} samples dropped",
+                swap_started.elapsed().as_micros(),
+                self.dropped_samples() - dropped_before
+            );
+        }
+        self.stop_stream();
+        self.finalize_writer()?;
+        self.report_dropped();
+        self.log_summary();
+        self.take_write_errors()?;
+        Ok(files)
+    }
+
+    /// Sets the description embedded in a Broadcast Wave `bext` chunk on
+    /// every finalized file, along with the file's origination date and
+    /// time. No chunk is written when no description has been set.
+    pub fn set_description(&mut self, desc: String) {
+        self.description = Some(desc);
+    }
+
+    /// Sets a standard WAV `INFO` tag, written to every finalized wav
+    /// file as part of a `LIST INFO` chunk so cataloging tools can read
+    /// it without an external database entry. Common tag ids: `IART`
+    /// (artist/operator), `ICMT` (comment), `INAM` (title), `IPRD`
+    /// (project). Any four-character ASCII id is accepted; setting the
+    /// same id again replaces its value, and ids never set are omitted
+    /// from the chunk.
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let bytes = key.as_bytes();
+        if bytes.len() != 4 || !bytes.iter().all(|byte| byte.is_ascii_graphic()) {
+            return Err(anyhow!(
+                "INFO tag id must be four ASCII characters, got '{key}'"
+            ));
+        }
+        let id = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self.info_tags.iter_mut().find(|(existing, _)| *existing == id) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.info_tags.push((id, value.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Chooses whether filenames and metadata chunks carry local or UTC
+    /// timestamps. The default is local time, matching earlier behavior.
+    pub fn set_timezone(&mut self, mode: TimeZoneMode) {
+        self.timezone = mode;
+    }
+
+    /// Refuses to stamp files when the system clock reads a year before
+    /// `year`. Devices without an RTC boot to 1970 until NTP catches up,
+    /// and every filename and sidecar of the deployment would carry the
+    /// bogus date. With a fallback epoch configured (see
+    /// [`Self::set_clock_fallback`]) the session records anyway, rebased
+    /// onto the fallback and flagged as untrusted in the sidecar;
+    /// without one, starting a file fails with an error.
+    pub fn set_min_clock_year(&mut self, year: i32) {
+        self.min_clock_year = Some(year);
+    }
+
+    /// Epoch to rebase timestamps onto when the clock fails the minimum
+    /// year check, e.g. the deployment date. The wall clock still ticks
+    /// on such devices — it just started from the wrong point — so
+    /// files keep distinct, monotonic stamps measured from the fallback.
+    pub fn set_clock_fallback(&mut self, epoch: DateTime<Local>) {
+        self.clock_fallback = Some(epoch);
+    }
+
+    /// Applies the minimum-year clock check to a file's start timestamp,
+    /// returning the timestamp files should actually be stamped with.
+    fn check_clock(&mut self, started: DateTime<Local>) -> Result<DateTime<Local>, Error> {
+        let Some(min_year) = self.min_clock_year else {
+            return Ok(started);
+        };
+        // Once rebased, every later file shifts by the same offset so
+        // the stamps stay consistent within the session.
+        if let Some(offset) = self.clock_offset {
+            return Ok(started + offset);
+        }
+        if started.year() >= min_year {
+            return Ok(started);
+        }
+        let Some(fallback) = self.clock_fallback else {
+            return Err(anyhow!(
+                "system clock reads {}, before the configured minimum year {}; \
+                 refusing to stamp files with it",
+                started.to_rfc3339(),
+                min_year
+            ));
+        };
+        log::warn!(
+            "system clock reads {}; rebasing file timestamps onto the fallback epoch {}",
+            started.to_rfc3339(),
+            fallback.to_rfc3339()
+        );
+        self.clock_untrusted = true;
+        self.clock_offset = Some(fallback - started);
+        Ok(fallback)
+    }
+
+    /// Formats `timestamp` in the configured time zone.
+    fn format_timestamp(&self, timestamp: &DateTime<Local>, fmt: &str) -> String {
+        match self.timezone {
+            TimeZoneMode::Local => timestamp.format(fmt).to_string(),
+            TimeZoneMode::Utc => timestamp.with_timezone(&Utc).format(fmt).to_string(),
+        }
+    }
+
+    /// Sets the filename template used for new files, so recordings can
+    /// match whatever naming convention a downstream archive expects. The
+    /// supported placeholders are `{name}`, `{date}`, `{time}`, `{index}`
+    /// (a zero-padded per-run counter), and `{ext}`; anything else is
+    /// rejected here rather than failing mid-recording. The default is
+    /// equivalent to `{name}_{date}_{time}.{ext}`.
+    pub fn set_filename_template(&mut self, template: String) -> Result<(), Error> {
+        validate_template(&template)?;
+        self.filename_template = Some(template);
+        Ok(())
+    }
+
+    /// Continues the most recent of this recorder's wav files in the
+    /// output directory instead of creating a new one, picking up where a
+    /// crash-restart left off rather than leaving a gap and a fresh file.
+    /// The existing header's spec must match the configured capture
+    /// parameters, and only plain wav output can be appended to; when no
+    /// earlier file exists a new one is created as usual. Finalize
+    /// updates the header sizes to cover the old and new data alike.
+    pub fn set_append(&mut self, enabled: bool) {
+        self.append = enabled;
+    }
+
+    /// Places new files in `YYYY/MM/DD/` subdirectories under the output
+    /// path, created as needed, instead of one flat directory. Months of
+    /// unattended batching otherwise pile thousands of files into a
+    /// single directory, which slows sync tooling and directory listings
+    /// alike. The date uses the configured time zone and the same
+    /// (possibly rebased) timestamp as the filename, so a file and its
+    /// folder never disagree about the day.
+    pub fn set_date_folders(&mut self, enabled: bool) {
+        self.date_folders = enabled;
+    }
+
+    /// Permits falling back to a lower capture rate when the requested
+    /// one fails to open, trying standard rates below it highest-first
+    /// and logging the downgrade. For unattended deployments, recording
+    /// at a reduced rate beats recording nothing. Applies to plain wav
+    /// output; off by default.
+    pub fn set_allow_downgrade(&mut self, enabled: bool) {
+        self.allow_downgrade = enabled;
+    }
+
+    /// Rebuilds the stream automatically when the error callback reports
+    /// the capture device gone, e.g. after a USB brown-out. Reconnects are
+    /// retried with exponential backoff and recording resumes into a new
+    /// timestamped file; the count of reconnects is reported at stop.
+    pub fn set_auto_reconnect(&mut self, enabled: bool) {
+        self.auto_reconnect = enabled;
+    }
+
+    /// Treats the stream as failed when no buffers arrive for `secs`
+    /// seconds. A wedged device does not always fire the error callback;
+    /// without a timeout such a stall records silence-free nothing
+    /// forever. On expiry the stall is handled like a reported device
+    /// loss: reconnect when auto-reconnect is enabled, otherwise a clean
+    /// stop with the file finalized and an error returned.
+    pub fn set_stream_timeout_secs(&mut self, secs: u64) {
+        self.stream_timeout = Some(Duration::from_secs(secs));
+    }
+
+    /// Resamples captured audio to `rate` before it is written, so files
+    /// carry exactly the rate analysis tools expect regardless of what the
+    /// ADC offers. Resampling runs on a worker thread fed from the audio
+    /// callback and adds roughly one chunk (1024 input frames) of latency
+    /// to the written data. A no-op when `rate` matches the capture rate.
+    /// Does not combine with integer decimation.
+    pub fn set_target_sample_rate(&mut self, rate: u32) -> Result<(), Error> {
+        if self.decimation.is_some() {
+            return Err(anyhow!("resampling does not combine with decimation"));
+        }
+        self.target_sample_rate = Some(rate);
+        Ok(())
+    }
+
+    /// Chooses the on-disk format for new files. FLAC, Opus, and Vorbis
+    /// encoding run on a worker thread fed from the audio callback, so the
+    /// compression cost never lands on the capture path; filenames switch
+    /// extension through the usual template handling. Encoded formats do
+    /// not combine with resampling, triggered capture, or mid-stream
+    /// splitting — those paths keep their wav writer and report an error
+    /// instead of silently changing format.
+    pub fn set_format(&mut self, format: OutputFormat) {
+        self.format = format;
+    }
+
+    /// Sets the Opus target bitrate in bits per second, trading upload
+    /// volume against fidelity. Only meaningful with
+    /// [`OutputFormat::Opus`]; the default is 32 kbit/s.
+    pub fn set_opus_bitrate(&mut self, bits_per_second: u32) {
+        self.opus_bitrate = bits_per_second;
+    }
+
+    /// Records only the given interleaved channel indices (0-based), e.g.
+    /// `[2]` keeps just the third input channel. The output wav carries one
+    /// channel per selected index, in the order given. Indices must lie
+    /// within the configured channel count.
+    pub fn set_channel_selection(&mut self, channels: Vec<u16>) -> Result<(), Error> {
+        if channels.is_empty() {
+            return Err(anyhow!("channel selection must not be empty"));
+        }
+        if let Some(&bad) = channels.iter().find(|&&c| c >= self.user_config.channels) {
+            return Err(anyhow!(
+                "channel index {} out of range, input has {} channels",
+                bad,
+                self.user_config.channels
+            ));
+        }
+        self.channel_selection = Some(channels);
+        Ok(())
+    }
+
+    /// High-passes all captured audio before any other processing or
+    /// metering, removing the ADC's DC offset and low-frequency flow
+    /// noise. The filter is a second-order Butterworth biquad: -3 dB at
+    /// `cutoff_hz`, 12 dB/octave rolloff below it (see the `highpass`
+    /// module). Per-channel state carries across buffer boundaries, so
+    /// the output is continuous. The cutoff must lie below Nyquist.
+    pub fn set_highpass_hz(&mut self, cutoff_hz: f32) -> Result<(), Error> {
+        let nyquist = self.user_config.sample_rate.0 as f32 / 2.0;
+        if !(cutoff_hz > 0.0 && cutoff_hz < nyquist) {
+            return Err(anyhow!(
+                "high-pass cutoff must lie between 0 and {} Hz, got {}",
+                nyquist,
+                cutoff_hz
+            ));
+        }
+        self.highpass_hz = Some(cutoff_hz);
+        Ok(())
+    }
+
+    /// Divides the output sample rate by an integer `factor`, low-pass
+    /// filtering against aliasing and keeping every Nth frame (see the
+    /// `decimate` module). Cheaper and more predictable than arbitrary
+    /// resampling when only low-frequency content matters, e.g. sub-1 kHz
+    /// infrasound studies captured on a high-rate ADC. The factor must be
+    /// at least 2, divide the capture rate exactly, and does not combine
+    /// with resampling.
+    pub fn set_decimation(&mut self, factor: u32) -> Result<(), Error> {
+        if factor < 2 {
+            return Err(anyhow!("decimation factor must be at least 2, got {factor}"));
+        }
+        if !self.user_config.sample_rate.0.is_multiple_of(factor) {
+            return Err(anyhow!(
+                "decimation factor {} does not divide the capture rate {}",
+                factor,
+                self.user_config.sample_rate.0
+            ));
+        }
+        if self.target_sample_rate.is_some() {
+            return Err(anyhow!("decimation does not combine with resampling"));
+        }
+        self.decimation = Some(factor);
+        Ok(())
+    }
+
+    /// Replaces the first `secs` seconds of each recording with a
+    /// synthesized reference tone at `freq_hz` and -6 dBFS, giving every
+    /// file a per-file amplitude reference for calibrated measurements.
+    /// The tone is generated at the configured sample rate and channel
+    /// count and hands over to live input on an exact sample boundary,
+    /// which the sidecar records along with the tone parameters. The
+    /// frequency must lie below Nyquist.
+    pub fn inject_test_tone(&mut self, freq_hz: f32, secs: u64) -> Result<(), Error> {
+        let nyquist = self.user_config.sample_rate.0 as f32 / 2.0;
+        if !(freq_hz > 0.0 && freq_hz < nyquist) {
+            return Err(anyhow!(
+                "test tone frequency must lie between 0 and {} Hz, got {}",
+                nyquist,
+                freq_hz
+            ));
+        }
+        self.test_tone = Some((freq_hz, secs));
+        Ok(())
+    }
+
+    /// Writes each output channel into its own mono wav file instead of
+    /// one interleaved file, as array processing tools often expect.
+    /// Filenames get a `_chN` suffix (1-based, in output channel order,
+    /// so after any channel selection). Splitting applies to wav output
+    /// only and does not combine with mono downmix, resampling, or the
+    /// triggered and mid-stream splitting modes.
+    pub fn set_split_channels(&mut self, split: bool) {
+        self.split_channels = split;
+    }
+
+    /// Averages all input channels into a single mono channel before
+    /// writing, halving (or better) the storage of multi-channel input.
+    /// A no-op when the input is already mono.
+    pub fn set_downmix_mono(&mut self, downmix: bool) {
+        self.downmix = downmix;
+    }
+
+    /// Stops recording cleanly (finalizing the current file) once free
+    /// space on the output filesystem drops below `bytes`, instead of
+    /// letting writes fail and corrupt the session.
+    pub fn set_min_free_bytes(&mut self, bytes: u64) {
+        self.min_free_bytes = Some(bytes);
+    }
+
+    /// Rolls to a new timestamped file once the current one holds `bytes`
+    /// of sample data, independent of any time-based split, for upload
+    /// pipelines and filesystems that prefer bounded file sizes. The
+    /// stream keeps running across the boundary, so no samples are lost;
+    /// limits above the wav format's own ceiling are capped to it.
+    pub fn set_max_file_bytes(&mut self, bytes: u64) {
+        self.max_file_bytes = Some(bytes);
+    }
+
+    /// The data byte count at which the current file rolls over: the
+    /// configured maximum when one is set, always capped by the wav
+    /// format limit.
+    fn roll_bytes(&self) -> u64 {
+        self.max_file_bytes
+            .map_or(MAX_WAV_BYTES, |max| max.min(MAX_WAV_BYTES))
+    }
+
+    /// Redirects new files to a different output directory, e.g. a freshly
+    /// mounted USB drive, without restarting the logger. The change takes
+    /// effect when the next file is opened; a file already in progress
+    /// finishes in its original directory. The directory is probed with a
+    /// throwaway write before being accepted, so a read-only or missing
+    /// mount is rejected here instead of failing mid-session.
+    pub fn set_output_path(&mut self, path: PathBuf) -> Result<(), Error> {
+        let probe = path.join(format!(".{}-write-probe", self.name));
+        std::fs::write(&probe, b"")
+            .map_err(|err| anyhow!("output path {} is not writable: {}", path.display(), err))?;
+        std::fs::remove_file(&probe)?;
+        log::info!("STATUS output path changes to {} at next file", path.display());
+        self.pending_path = Some(path);
+        Ok(())
+    }
+
+    /// Restricts recording to a daily time-of-day window, e.g. dusk to
+    /// dawn for nocturnal species. The batch and duty-cycle loops wait
+    /// with the device released while the local clock is outside the
+    /// window and resume when it reopens. Windows where `end` precedes
+    /// `start` cross midnight, so 18:00–06:00 records through the night.
+    pub fn set_active_window(&mut self, start: NaiveTime, end: NaiveTime) {
+        self.active_window = Some((start, end));
+    }
+
+    /// Returns true when no window is configured or the local clock is
+    /// currently inside it.
+    pub fn in_active_window(&self) -> bool {
+        let Some((start, end)) = self.active_window else {
+            return true;
+        };
+        let now = Local::now().time();
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+
+    /// Blocks until the active window opens, checking the clock once a
+    /// second so the wait stays responsive. Returns true when an interrupt
+    /// arrived while waiting, mirroring [`Self::sleep_interruptible`]. A
+    /// no-op when no window is configured or it is already open.
+    pub fn wait_for_window(&self) -> bool {
+        if !self.in_active_window() {
+            log::info!("STATUS outside active window, waiting");
+        }
+        while !self.in_active_window() {
+            if self.sleep_interruptible(1) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Keeps only the most recent recordings under the given budget,
+    /// deleting this recorder's oldest files after each finalize instead
+    /// of stopping when the disk fills. Only audio files whose names
+    /// start with the recorder's name and whose extension is one the
+    /// recorder writes are considered, so sidecars, the batch-state
+    /// file, and other tenants of the directory are left alone.
+    pub fn set_retention(&mut self, policy: RetentionPolicy) {
+        self.retention = Some(policy);
+    }
+
+    /// Persists the progress of budgeted batch runs (segments recorded,
+    /// budget time consumed) to a state file in the output directory, so
+    /// a restart after a brownout resumes toward the remaining budget
+    /// instead of starting the experiment over. The state file is removed
+    /// once the budget completes; a corrupt or missing file starts fresh
+    /// with a warning.
+    pub fn set_batch_persistence(&mut self, enabled: bool) {
+        self.persist_batch = enabled;
+    }
+
+    pub(crate) fn batch_persistence(&self) -> bool {
+        self.persist_batch
+    }
+
+    fn batch_state_path(&self) -> PathBuf {
+        self.path.join(format!("{}.batch-state.json", self.name))
+    }
+
+    /// Loads persisted batch progress, or a fresh zero state when none
+    /// exists yet. A state file that cannot be read or parsed also starts
+    /// fresh, with a warning — better to over-record after a corrupted
+    /// shutdown than to silently cut an experiment short.
+    pub(crate) fn load_batch_state(&self) -> BatchState {
+        let path = self.batch_state_path();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return BatchState::default(),
+            Err(err) => {
+                log::warn!(
+                    "could not read batch state {}: {}; starting fresh",
+                    path.display(),
+                    err
+                );
+                return BatchState::default();
+            }
+        };
+        match serde_json::from_str(&text) {
+            Ok(state) => {
+                let BatchState { files, elapsed_secs } = state;
+                log::info!(
+                    "resuming batch: {} segments and {}s of budget already recorded",
+                    files,
+                    elapsed_secs
+                );
+                state
+            }
+            Err(err) => {
+                log::warn!(
+                    "corrupt batch state {}: {}; starting fresh",
+                    path.display(),
+                    err
+                );
+                BatchState::default()
+            }
+        }
+    }
+
+    /// Saves batch progress after each segment. Failures are logged, not
+    /// fatal: losing resume state must not stop the recording producing
+    /// it.
+    pub(crate) fn save_batch_state(&self, state: BatchState) {
+        let path = self.batch_state_path();
+        let write = serde_json::to_string(&state)
+            .map_err(Error::from)
+            .and_then(|text| std::fs::write(&path, text).map_err(Error::from));
+        if let Err(err) = write {
+            log::warn!("could not save batch state {}: {}", path.display(), err);
+        }
+    }
+
+    /// Removes the state file once a batch budget completes, so the next
+    /// run starts a new experiment.
+    pub(crate) fn clear_batch_state(&self) {
+        let path = self.batch_state_path();
+        if let Err(err) = std::fs::remove_file(&path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("could not remove batch state {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// Returns true when recording stopped because the output filesystem
+    /// ran below the configured free-space threshold.
+    pub fn low_disk(&self) -> bool {
+        self.low_disk
+    }
+
+    /// Sets the deployment position embedded into every finalized file as
+    /// an `iXML` chunk. Files recorded without a location set carry no
+    /// extra chunk.
+    pub fn set_location(&mut self, lat: f64, lon: f64, depth_m: Option<f32>) {
+        self.location = Some(Location { lat, lon, depth_m });
+    }
+
+    /// Delivers per-channel RMS and peak levels for every captured buffer
+    /// to `callback`, e.g. for a live VU display. The callback runs on its
+    /// own thread; readings are dropped rather than blocking the audio
+    /// thread when the callback cannot keep up.
+    pub fn set_level_callback(&mut self, callback: impl Fn(LevelInfo) + Send + 'static) {
+        let (tx, rx) = mpsc::sync_channel(LEVEL_QUEUE_DEPTH);
+        thread::spawn(move || {
+            for info in rx {
+                callback(info);
+            }
+        });
+        self.level_tx = Some(tx);
+    }
+
+    /// Records the arrival time of every captured buffer to a
+    /// `<filename>.timeline.csv` sidecar next to each recorded file, one
+    /// `sample_offset,monotonic_secs,unix_secs` row per buffer, so
+    /// downstream analysis can map any sample to an absolute time even
+    /// when the stream stalled or reconnected mid-file. Offsets count
+    /// interleaved samples from the start of the file; divide by the
+    /// channel count for frames. Rows are written on their own thread
+    /// and dropped rather than blocking the audio thread when it cannot
+    /// keep up.
+    pub fn set_buffer_timestamps(&mut self, enabled: bool) {
+        if !enabled {
+            self.timeline_tx = None;
+            return;
+        }
+        let file = Arc::clone(&self.timeline_file);
+        let base = Arc::clone(&self.timeline_base);
+        let (tx, rx) = mpsc::sync_channel::<TimelineEntry>(TIMELINE_QUEUE_DEPTH);
+        let anchor = Instant::now();
+        thread::spawn(move || {
+            for entry in rx {
+                let Ok(mut guard) = file.lock() else {
+                    return;
+                };
+                // Between files the sidecar is closed; entries arriving
+                // then belong to no file and are discarded.
+                let Some(out) = guard.as_mut() else {
+                    continue;
+                };
+                let offset = entry.samples.saturating_sub(base.load(Ordering::Relaxed));
+                let mono = entry.mono.duration_since(anchor).as_secs_f64();
+                let wall = entry
+                    .wall
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0.0, |since| since.as_secs_f64());
+                if let Err(err) = writeln!(out, "{},{:.6},{:.6}", offset, mono, wall) {
+                    log::warn!("writing timeline sidecar failed: {}; disabling it", err);
+                    *guard = None;
+                }
+            }
+        });
+        self.timeline_tx = Some(tx);
+    }
+
+    /// Opens the timeline sidecar for the file just created and anchors
+    /// sample offsets to the session total at that moment.
+    fn open_timeline(&self, filename: &str) -> Result<(), Error> {
+        if self.timeline_tx.is_none() {
+            return Ok(());
+        }
+        self.timeline_base
+            .store(self.total_samples.load(Ordering::Relaxed), Ordering::Relaxed);
+        let mut out = BufWriter::new(File::create(format!("{}.timeline.csv", filename))?);
+        writeln!(out, "sample_offset,monotonic_secs,unix_secs")?;
+        *self
+            .timeline_file
+            .lock()
+            .map_err(|_| RecorderError::Poisoned)? = Some(out);
+        Ok(())
+    }
+
+    /// Closes the timeline sidecar, flushing any buffered rows.
+    fn close_timeline(&self) {
+        if let Ok(mut guard) = self.timeline_file.lock() {
+            if let Some(mut out) = guard.take() {
+                if let Err(err) = out.flush() {
+                    log::warn!("flushing timeline sidecar failed: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Hands every captured buffer to `callback` as normalized f32
+    /// samples, interleaved in capture order, alongside the stream config
+    /// describing the layout — for embedders running their own DSP on the
+    /// live signal. Unlike the metering taps this is not decoupled by a
+    /// queue: the callback runs on the audio thread between the device
+    /// and the file write, so it must return quickly and defer anything
+    /// heavy to its own thread, or samples will be dropped. Buffers are
+    /// delivered where the metering taps sit: after any high-pass or
+    /// decimation stage, before gain and channel processing.
+    pub fn set_buffer_callback(
+        &mut self,
+        callback: impl Fn(&[f32], &StreamConfig) + Send + Sync + 'static,
+    ) {
+        self.buffer_cb = Some(Arc::new(callback));
+    }
+
+    /// Applies a fixed gain (in dB) to all recorded samples. Amplified
+    /// samples are clamped to full scale instead of wrapping around, and a
+    /// warning is printed at stop if any sample clipped.
+    pub fn set_gain(&mut self, gain_db: f32) {
+        let linear = 10f32.powf(gain_db / 20.0);
+        self.gain.store(linear.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Softens clipping with a cubic saturation curve instead of the hard
+    /// clamp, for deployments where the gain cannot be tuned precisely in
+    /// the field. Transients past full scale saturate smoothly rather
+    /// than squaring off, keeping harsh clipping harmonics out of
+    /// spectral analysis; samples near full scale are shaved slightly in
+    /// exchange. Off by default.
+    pub fn set_soft_clip(&mut self, enabled: bool) {
+        self.soft_clip = enabled;
+    }
+
+    /// Adds TPDF dither ahead of any bit-depth reduction, trading the
+    /// quantization distortion audible in quiet passages for a flat noise
+    /// floor one LSB high. Engages only when the file's bit depth is
+    /// lower than what the device delivers; lossless pass-through stays
+    /// untouched. Off by default.
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.dither = enabled;
+    }
+
+    /// Builds the shared dither source for the next stream, or None when
+    /// dithering is off or the output depth loses no bits relative to the
+    /// device's sample format.
+    fn dither_source(&self) -> Result<Option<Arc<Dither>>, Error> {
+        if !self.dither {
+            return Ok(None);
+        }
+        let spec = self.get_wav_spec()?;
+        if spec.sample_format != hound::SampleFormat::Int {
+            return Ok(None);
+        }
+        let input_bits = match self.default_config.sample_format() {
+            SampleFormat::F32 => 32,
+            SampleFormat::I32 => 24,
+            SampleFormat::I16 | SampleFormat::U16 => 16,
+            SampleFormat::I8 | SampleFormat::U8 => 8,
+            _ => 32,
+        };
+        if spec.bits_per_sample >= input_bits {
+            return Ok(None);
+        }
+        Ok(Some(Arc::new(Dither::new(spec.bits_per_sample))))
+    }
+
+    /// Applies an independent gain factor per input channel, indexed in
+    /// interleaved order, so hydrophones of different sensitivity can be
+    /// balanced into matched levels. Channel gains are applied before
+    /// selection, downmix, and the global gain; amplified samples clip at
+    /// full scale and set the same clip flag as the global gain. The
+    /// vector length must match the capture channel count.
+    pub fn set_channel_gains(&mut self, gains: Vec<f32>) -> Result<(), Error> {
+        if gains.len() != self.user_config.channels as usize {
+            return Err(anyhow!(
+                "expected {} channel gains, got {}",
+                self.user_config.channels,
+                gains.len()
+            ));
+        }
+        self.channel_gains = Some(Arc::new(gains));
+        Ok(())
+    }
+
+    /// Discards triggered recordings shorter than `secs` seconds once
+    /// their hangover elapses, deleting the file instead of keeping it.
+    /// Clicks and electrical pops open a file like a genuine call does;
+    /// this drops them at close, judged by the measured duration of what
+    /// was actually written rather than the time the file was open.
+    pub fn set_min_event_secs(&mut self, secs: u64) {
+        self.min_event_secs = Some(secs);
+    }
+
+    /// Retains the last `secs` seconds of audio while idle in triggered
+    /// mode, so each triggered file starts with the audio leading up to the
+    /// trigger instead of cutting off the event onset.
+    pub fn set_pretrigger_secs(&mut self, secs: u64) -> Result<(), Error> {
+        let samples = secs as usize
+            * self.user_config.sample_rate.0 as usize
+            * self.user_config.channels as usize;
+        *self
+            .pretrigger
+            .lock()
+            .map_err(|_| RecorderError::Poisoned)? = VecDeque::with_capacity(samples);
+        Ok(())
+    }
+
+    /// Records only while the input level exceeds `threshold` (peak
+    /// amplitude, full scale 1.0). Each triggered event is written to its
+    /// own timestamped file, which is closed once the level has stayed
+    /// below the threshold for `hangover_secs`. Runs until interrupted.
+    pub fn record_triggered(&mut self, threshold: f32, hangover_secs: u64) -> Result<(), Error> {
+        if self.format != OutputFormat::Wav {
+            return Err(anyhow!("triggered recording only supports wav output"));
+        }
+        if self.split_channels {
+            return Err(anyhow!(
+                "triggered recording does not combine with per-channel files"
+            ));
+        }
+        // The ring path writes whatever it holds whenever a writer is
+        // open: the pre-trigger buffer would never fill and stale idle
+        // audio would open every event file.
+        if self.lock_free {
+            return Err(anyhow!(
+                "triggered recording does not combine with the lock-free writer"
+            ));
+        }
+        self.start_stream()?;
+        let mut last_above: Option<Instant> = None;
+        loop {
+            if self.interrupt_handles.stream_wait_timeout(TRIGGER_POLL_INTERVAL) {
+                break;
+            }
+            if self.write_errors() > 0 {
+                break;
+            }
+            let peak = self.take_peak();
+            let recording = self.lock_writer()?.is_some();
+            if peak >= threshold {
+                last_above = Some(Instant::now());
+                if !recording {
+                    self.init_writer_with_pretrigger()?;
+                    log::info!("REC: {}", self.current_file);
+                }
+            } else if recording {
+                let hangover_elapsed = last_above
+                    .is_none_or(|at| at.elapsed() >= Duration::from_secs(hangover_secs));
+                if hangover_elapsed {
+                    self.finalize_writer()?;
+                    self.discard_short_event()?;
+                }
+            }
+            if self.writer_bytes() >= self.roll_bytes() {
+                self.roll_writer()?;
+            }
+        }
+        self.stop_stream();
+        let event_open = self.lock_writer()?.is_some();
+        self.finalize_writer()?;
+        if event_open {
+            self.discard_short_event()?;
+        }
+        self.report_dropped();
+        self.take_write_errors()?;
+        Ok(())
+    }
+
+    /// Deletes the triggered file just finalized when its measured
+    /// duration falls short of the configured minimum event length, along
+    /// with its json sidecar. Called only from the triggered path, right
+    /// after an event's file is closed.
+    fn discard_short_event(&mut self) -> Result<(), Error> {
+        let Some(min) = self.min_event_secs else {
+            return Ok(());
+        };
+        let spec = self.get_wav_spec()?;
+        let duration_secs = self.samples_written() as f64
+            / spec.channels as f64
+            / spec.sample_rate as f64;
+        if duration_secs >= min as f64 {
+            return Ok(());
+        }
+        std::fs::remove_file(&self.current_file)?;
+        if self.sidecar {
+            let _ = std::fs::remove_file(Path::new(&self.current_file).with_extension("json"));
+        }
+        log::info!(
+            "DISCARD: {} ({:.2} s is under the {} s event minimum)",
+            self.current_file,
+            duration_secs,
+            min
+        );
+        Ok(())
+    }
+
+    /// Records a cue marker at the current write position of the open
+    /// file, so analysts can jump straight to flagged events in a DAW.
+    /// Markers are written into `cue ` and `LIST adtl` chunks when the
+    /// file is finalized; the offset is captured now, at the moment of the
+    /// call. Errors when no file is open or the output format is not wav.
+    pub fn add_marker(&self, label: String) -> Result<(), Error> {
+        if self.format != OutputFormat::Wav {
+            return Err(anyhow!("cue markers only support wav output"));
+        }
+        let offset = {
+            let guard = self.lock_writer()?;
+            let writer = guard
+                .as_ref()
+                .ok_or_else(|| anyhow!("no recording in progress to mark"))?;
+            writer.len() as u64 / writer.spec().channels as u64
+        };
+        self.markers
+            .lock()
+            .map_err(|_| RecorderError::Poisoned)?
+            .push(Marker { offset, label });
+        Ok(())
+    }
+
+    /// Pauses capture while keeping the current file open. Audio arriving
+    /// while paused is discarded by the device, not recorded as silence.
+    pub fn pause(&self) -> Result<(), Error> {
+        match &self.stream {
+            Some(stream) => {
+                stream.pause()?;
+                self.set_state(RecorderState::Paused);
+                Ok(())
+            }
+            None => Err(anyhow!("no recording in progress to pause")),
+        }
+    }
+
+    /// Resumes capture into the same file after a `pause`.
+    pub fn resume(&self) -> Result<(), Error> {
+        match &self.stream {
+            Some(stream) => {
+                stream.play()?;
+                self.set_state(RecorderState::Recording);
+                Ok(())
+            }
+            None => Err(anyhow!("no recording in progress to resume")),
+        }
+    }
+
+    /// Returns how long the active file has been recording, or None when
+    /// no file is open, so a UI can show a running timer without polling
+    /// the filesystem.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.file_started
+            .and_then(|started| Local::now().signed_duration_since(started).to_std().ok())
+    }
+
+    /// Returns what the recorder is doing right now, for UIs that poll
+    /// state alongside the event callbacks.
+    pub fn state(&self) -> RecorderState {
+        match self.state.load(Ordering::SeqCst) {
+            1 => RecorderState::Recording,
+            2 => RecorderState::Paused,
+            3 => RecorderState::Finalizing,
+            _ => RecorderState::Idle,
+        }
+    }
+
+    fn set_state(&self, state: RecorderState) {
+        self.state.store(state as u8, Ordering::SeqCst);
+    }
+
+    /// Shared counters and the output path, handed to the MQTT reporter
+    /// so its payloads carry health figures without borrowing the
+    /// recorder from the callback thread.
+    #[cfg(feature = "mqtt")]
+    pub(crate) fn telemetry(&self) -> crate::mqtt::Telemetry {
+        crate::mqtt::Telemetry {
+            dropped: Arc::clone(&self.dropped_samples),
+            reconnects: Arc::clone(&self.reconnects),
+            path: self.path.clone(),
+        }
+    }
+
+    /// Shared handle to the raw state atomic, for the GPIO status LED
+    /// thread which outlives any one borrow of the recorder.
+    #[cfg(feature = "rpi")]
+    pub(crate) fn state_handle(&self) -> Arc<AtomicU8> {
+        Arc::clone(&self.state)
+    }
+
+    /// Shared handle to the device-lost flag, so the status LED can blink
+    /// while the recorder waits to reconnect.
+    #[cfg(feature = "rpi")]
+    pub(crate) fn device_lost_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.device_lost)
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupt_handles.is_interrupted()
+    }
+
+    /// Returns a cloneable handle whose `stop` ends the recording just
+    /// like a console interrupt would.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.interrupt_handles.stop_handle()
+    }
+
+    /// Sleeps for `secs` seconds, returning early (with true) when an
+    /// interrupt arrives so idle loops stay responsive.
+    pub fn sleep_interruptible(&self, secs: u64) -> bool {
+        self.interrupt_handles
+            .stream_wait_timeout(Duration::from_secs(secs))
+    }
+
+    /// Returns the stream configuration the OS actually accepted, which
+    /// can differ from what was requested when validation downgraded an
+    /// unsupported sample rate or channel count.
+    pub fn effective_config(&self) -> &StreamConfig {
+        &self.user_config
+    }
+
+    /// Returns the wav spec files are written with — the negotiated rate
+    /// and channels after any selection, downmix, resampling, or
+    /// decimation, and the bit depth the device's sample format maps to.
+    pub fn effective_wav_spec(&self) -> Result<WavSpec, Error> {
+        self.get_wav_spec()
+    }
+
+    /// Returns the number of samples dropped so far because the writer was
+    /// busy or a write failed.
+    pub fn dropped_samples(&self) -> u64 {
+        self.dropped_samples.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of samples that reached the wav writer for the
+    /// file currently being recorded. Together with [`dropped_samples`]
+    /// this accounts for every captured sample, and divided by the
+    /// effective rate and channel count it gives the true recorded
+    /// duration rather than the requested one.
+    ///
+    /// [`dropped_samples`]: Recorder::dropped_samples
+    pub fn samples_written(&self) -> u64 {
+        self.samples_written.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of sample writes that failed outright (a
+    /// failing disk, not a busy writer) since init or the last error
+    /// returned for them.
+    pub fn write_errors(&self) -> u64 {
+        self.write_errors.load(Ordering::Relaxed)
+    }
+
+    /// Surfaces accumulated write failures as an I/O error, resetting the
+    /// counter so a retry against repaired storage starts clean. Called
+    /// after finalize, so everything recoverable is already on disk.
+    fn take_write_errors(&self) -> Result<(), Error> {
+        let errors = self.write_errors.swap(0, Ordering::Relaxed);
+        if errors > 0 {
+            return Err(std::io::Error::other(format!(
+                "{errors} sample writes failed mid-recording; the file holds what was recoverable"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the path of the most recently opened file, or None before
+    /// the first file has been started.
+    pub fn current_file(&self) -> Option<PathBuf> {
+        if self.current_file.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(&self.current_file))
+        }
+    }
+
+    /// Returns the peak level and clipped-sample count gathered since init,
+    /// for judging whether the input gain suits the signal.
+    pub fn clipping_stats(&self) -> ClipStats {
+        ClipStats {
+            peak: f32::from_bits(self.session_peak.load(Ordering::Relaxed)),
+            clipped_samples: self.clipped_samples.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Writes a `<filename>.json` sidecar next to each finalized
+    /// recording, carrying the capture parameters and counters so
+    /// provenance tooling does not have to parse wav chunks.
+    pub fn set_sidecar(&mut self, enabled: bool) {
+        self.sidecar = enabled;
+    }
+
+    /// Appends a row per finalized file to a `catalog.csv` manifest in
+    /// the output directory, created with a header when absent, so
+    /// ingestion tooling can read one file instead of scanning the tree.
+    /// Columns cover the path, start time, measured duration, format
+    /// parameters, dropped-sample count, and session peak level.
+    pub fn set_catalog_csv(&mut self, enabled: bool) {
+        self.catalog_csv = enabled;
+    }
+
+    /// Writes a `<filename>.png` spectrogram next to each finalized wav
+    /// file with default dimensions, for eyeballing a recording without
+    /// an analysis tool. Rendering reads the file back on a worker
+    /// thread, so the next segment starts without waiting for it. Only
+    /// wav files are rendered; encoded formats are skipped. Use
+    /// [`Self::set_spectrogram_config`] to control dimensions, frequency
+    /// range, and the dB floor.
+    pub fn set_spectrogram_png(&mut self, enabled: bool) {
+        self.spectrogram = enabled.then(SpectrogramConfig::default);
+    }
+
+    /// Enables per-file spectrograms like [`Self::set_spectrogram_png`],
+    /// rendered with the given parameters instead of the defaults.
+    pub fn set_spectrogram_config(&mut self, config: SpectrogramConfig) {
+        self.spectrogram = Some(config);
+    }
+
+    /// Rewrites each finalized wav in place so its peak hits `target`
+    /// (full scale 1.0), for quick listening at a consistent level. The
+    /// rewrite runs inside the finalize sequence before the checksum and
+    /// sidecar, so recorded hashes describe the normalized data, and it
+    /// adds two streaming passes over the file to the finalize cost. Off
+    /// by default — and to be left off for calibrated deployments, since
+    /// it scales the recorded data. Only plain wav output is normalized;
+    /// encoded and per-channel formats are skipped.
+    pub fn set_normalize_peak(&mut self, target: f32) -> Result<(), Error> {
+        if target <= 0.0 || target > 1.0 {
+            return Err(anyhow!(
+                "normalize target peak must lie in (0.0, 1.0], got {}",
+                target
+            ));
+        }
+        self.normalize_peak = Some(target);
+        Ok(())
+    }
+
+    /// Kicks off background spectrogram rendering for the file just
+    /// finalized, when configured and the format can be read back.
+    fn render_spectrogram(&self) {
+        let Some(config) = self.spectrogram else {
+            return;
+        };
+        if self.format != OutputFormat::Wav || self.split_channels {
+            return;
+        }
+        spectrogram::spawn_render(self.current_file.clone(), config);
+    }
+
+    /// Normalizes the file just finalized in place, when configured and
+    /// the format can be rewritten. Runs before the checksum and sidecar
+    /// are produced so they describe the normalized data; a failure is
+    /// logged and leaves the file as recorded rather than failing the
+    /// finalize.
+    fn normalize_finalized(&self) {
+        let Some(target) = self.normalize_peak else {
+            return;
+        };
+        if self.format != OutputFormat::Wav || self.split_channels {
+            return;
+        }
+        if let Err(err) = normalize::normalize(&self.current_file, target) {
+            log::warn!("normalizing {} failed: {:#}", self.current_file, err);
+        }
+    }
+
+    /// Rewrites the wav header and flushes buffered samples every `secs`
+    /// seconds while recording, so a power cut leaves a file whose header
+    /// matches the data written so far instead of claiming zero samples.
+    /// Costs one seek per flush; hours of audio are worth it on flaky
+    /// field power.
+    pub fn set_flush_interval_secs(&mut self, secs: u64) {
+        self.flush_interval = Some(Duration::from_secs(secs));
+    }
+
+    /// Routes captured audio through a lock-free single-producer ring
+    /// drained by a dedicated writer thread, instead of the callback
+    /// try-locking the writer mutex directly. With the direct path, a
+    /// finalize holding the lock costs a whole callback buffer; with the
+    /// ring, the callback never blocks and samples are only lost once the
+    /// ring itself fills. Only plain wav output uses the ring; encoded
+    /// formats already hand off to their worker threads.
+    pub fn set_lock_free_writer(&mut self, enabled: bool) {
+        self.lock_free = enabled;
+    }
+
+    /// Samples the lock-free writer thread drains from the ring per
+    /// writer-lock acquisition, 4096 by default. Larger chunks shave
+    /// per-write overhead on high-throughput deployments at the cost of
+    /// slightly more audio sitting in the ring before it reaches the
+    /// file; zero is treated as one. Only meaningful together with
+    /// [`Self::set_lock_free_writer`].
+    pub fn set_write_chunk_samples(&mut self, samples: usize) {
+        self.write_chunk_samples = samples.max(1);
+    }
+
+    /// Chooses how hard written audio is pushed toward the disk; see
+    /// [`DurabilityMode`]. Off (`Buffered`) by default.
+    pub fn set_durability(&mut self, mode: DurabilityMode) {
+        self.durability = mode;
+    }
+
+    /// Flushes the writer and fsyncs the underlying file when the
+    /// configured fsync interval has elapsed, so the audio is on stable
+    /// storage and not just in OS caches. Errors are logged, not fatal,
+    /// matching the flush path. Only the main wav writer is synced;
+    /// encoded formats buffer on their worker threads instead.
+    fn sync_if_due(&mut self) {
+        let DurabilityMode::Fsync { every_secs } = self.durability else {
+            return;
+        };
+        if self.last_sync.elapsed() < Duration::from_secs(every_secs) {
+            return;
+        }
+        self.last_sync = Instant::now();
+        if let Ok(mut guard) = self.writer.lock() {
+            if let Some(writer) = guard.as_mut() {
+                if let Err(err) = writer.flush() {
+                    log::error!("flushing wav data failed: {}", err);
+                }
+            }
+        }
+        if let Some(file) = &self.sync_file {
+            if let Err(err) = file.sync_all() {
+                log::error!("fsync of output file failed: {}", err);
+            }
+        }
+    }
+
+    /// Flushes the writer when the configured interval has elapsed. A
+    /// failed flush is logged, not fatal: the recording itself can still
+    /// finalize normally.
+    fn flush_if_due(&mut self) {
+        let Some(interval) = self.flush_interval else {
+            return;
+        };
+        if self.last_flush.elapsed() < interval {
+            return;
+        }
+        self.last_flush = Instant::now();
+        if let Ok(mut guard) = self.writer.lock() {
+            if let Some(writer) = guard.as_mut() {
+                if let Err(err) = writer.flush() {
+                    log::error!("flushing wav header failed: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Computes a SHA-256 of each finalized file and reports it through
+    /// the `FileStopped` event and the sidecar, so archival transfers can
+    /// be verified without a separate hashing step. The hash is one
+    /// streamed pass over the file right after finalize.
+    pub fn set_checksum(&mut self, enabled: bool) {
+        self.checksum = enabled;
+    }
+
+    /// Measures each file's integrated loudness as plain RMS in dBFS,
+    /// computed over the samples as written (post-gain, post-filter,
+    /// post-downmix) and reported through the sidecar, the stop log line,
+    /// and [`Self::last_rms_dbfs`]. The figure gives one comparable
+    /// received-level number per recording. Accumulation runs on a worker
+    /// thread; a full-scale sine reads -3 dB.
+    pub fn set_loudness(&mut self, enabled: bool) {
+        if !enabled {
+            self.loudness_tx = None;
+            self.loudness_accum = None;
+            return;
+        }
+        let accum = Arc::new(Mutex::new((0.0f64, 0u64)));
+        let worker = Arc::clone(&accum);
+        let (tx, rx) = mpsc::sync_channel::<Vec<f32>>(LOUDNESS_QUEUE_DEPTH);
+        thread::spawn(move || {
+            for buffer in rx {
+                let energy: f64 = buffer.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+                if let Ok(mut accum) = worker.lock() {
+                    accum.0 += energy;
+                    accum.1 += buffer.len() as u64;
+                }
+            }
+        });
+        self.loudness_tx = Some(tx);
+        self.loudness_accum = Some(accum);
+    }
+
+    /// Returns the RMS loudness of the most recently finalized file, when
+    /// loudness measurement is enabled.
+    pub fn last_rms_dbfs(&self) -> Option<f32> {
+        self.last_rms_dbfs
+    }
+
+    /// Drains the loudness accumulator into a dBFS figure, resetting it so
+    /// the next file starts from zero.
+    fn take_loudness(&self) -> Option<f32> {
+        let accum = self.loudness_accum.as_ref()?;
+        let mut accum = accum.lock().ok()?;
+        let (energy, samples) = std::mem::take(&mut *accum);
+        if samples == 0 {
+            return None;
+        }
+        Some((10.0 * (energy / samples as f64).log10()) as f32)
+    }
+
+    /// Measures per-file agreement between the first two capture
+    /// channels — correlation coefficient and RMS level difference — to
+    /// catch a dead or miswired array element early. The figures land in
+    /// the sidecar, the finalize log, and
+    /// [`Self::last_channel_balance`], with a warning when the channels
+    /// look uncorrelated or badly imbalanced. Accumulation runs on a
+    /// worker thread over the raw capture signal, before gain or channel
+    /// processing. Requires at least two capture channels.
+    pub fn set_balance_check(&mut self, enabled: bool) -> Result<(), Error> {
+        if !enabled {
+            self.balance_tx = None;
+            self.balance_accum = None;
+            return Ok(());
+        }
+        if self.user_config.channels < 2 {
+            return Err(anyhow!(
+                "the balance check needs at least two capture channels"
+            ));
+        }
+        let accum = Arc::new(Mutex::new(BalanceAccum::default()));
+        let worker = Arc::clone(&accum);
+        let channels = self.user_config.channels as usize;
+        let (tx, rx) = mpsc::sync_channel::<Vec<f32>>(BALANCE_QUEUE_DEPTH);
+        thread::spawn(move || {
+            for buffer in rx {
+                let Ok(mut accum) = worker.lock() else {
+                    return;
+                };
+                for frame in buffer.chunks_exact(channels) {
+                    let (a, b) = (f64::from(frame[0]), f64::from(frame[1]));
+                    accum.sum_a += a;
+                    accum.sum_b += b;
+                    accum.sum_aa += a * a;
+                    accum.sum_bb += b * b;
+                    accum.sum_ab += a * b;
+                    accum.frames += 1;
+                }
+            }
+        });
+        self.balance_tx = Some(tx);
+        self.balance_accum = Some(accum);
+        Ok(())
+    }
+
+    /// Returns the channel balance of the most recently finalized file,
+    /// when the balance check is enabled.
+    pub fn last_channel_balance(&self) -> Option<ChannelBalance> {
+        self.last_balance
+    }
+
+    /// Drains the balance accumulator into per-file figures, resetting it
+    /// so the next file starts from zero. Returns `None` when no frames
+    /// accumulated or one channel carried no energy at all.
+    fn take_balance(&self) -> Option<ChannelBalance> {
+        let accum = self.balance_accum.as_ref()?;
+        let mut accum = accum.lock().ok()?;
+        let BalanceAccum {
+            sum_a,
+            sum_b,
+            sum_aa,
+            sum_bb,
+            sum_ab,
+            frames,
+        } = std::mem::take(&mut *accum);
+        if frames == 0 || sum_aa == 0.0 || sum_bb == 0.0 {
+            return None;
+        }
+        let n = frames as f64;
+        let var_a = sum_aa - sum_a * sum_a / n;
+        let var_b = sum_bb - sum_b * sum_b / n;
+        let cov = sum_ab - sum_a * sum_b / n;
+        let denom = (var_a * var_b).sqrt();
+        let correlation = if denom > 0.0 { (cov / denom) as f32 } else { 0.0 };
+        Some(ChannelBalance {
+            correlation,
+            level_diff_db: (10.0 * (sum_aa / sum_bb).log10()) as f32,
+        })
+    }
+
+    /// Logs the per-file balance figures, flagging readings that usually
+    /// mean a wiring fault.
+    fn report_balance(&self) {
+        let Some(balance) = self.last_balance else {
+            return;
+        };
+        log::info!(
+            "channel balance: correlation {:.2}, level difference {:+.1} dB",
+            balance.correlation,
+            balance.level_diff_db
+        );
+        if balance.correlation < BALANCE_MIN_CORRELATION {
+            log::warn!(
+                "channels look uncorrelated (correlation {:.2}); check the array wiring",
+                balance.correlation
+            );
+        }
+        if balance.level_diff_db.abs() > BALANCE_MAX_IMBALANCE_DB {
+            log::warn!(
+                "channel levels differ by {:+.1} dB; one element may be dead or attenuated",
+                balance.level_diff_db
+            );
+        }
+    }
+
+    /// Streams processed PCM to a listener at `addr` while recording, for
+    /// live monitoring from shore. The connection carries a 12-byte
+    /// header — magic `HPCM`, sample rate (u32), channel count (u16), and
+    /// bits per sample (u16), all little-endian — followed by interleaved
+    /// f32 little-endian samples with gain, selection, and downmix
+    /// already applied. Sending runs on its own thread; when the network
+    /// cannot keep up, frames are dropped and counted (see
+    /// [`Self::tcp_dropped_samples`]) rather than stalling capture. A
+    /// send failure ends the tap with an error logged; recording
+    /// continues unaffected.
+    pub fn stream_tcp(&mut self, addr: SocketAddr) -> Result<(), Error> {
+        let spec = self.get_wav_spec()?;
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(&tcp_header(&spec))?;
+        let (tx, rx) = mpsc::sync_channel::<Vec<f32>>(TCP_QUEUE_DEPTH);
+        thread::spawn(move || {
+            for buffer in rx {
+                let mut bytes = Vec::with_capacity(buffer.len() * 4);
+                for sample in buffer {
+                    bytes.extend_from_slice(&sample.to_le_bytes());
+                }
+                if let Err(err) = stream.write_all(&bytes) {
+                    log::error!("tcp stream to listener failed: {}", err);
+                    break;
+                }
+            }
+        });
+        self.tcp_tx = Some(tx);
+        Ok(())
+    }
+
+    /// Returns the number of samples dropped from the TCP stream because
+    /// the network could not keep up. Streamed drops are counted apart
+    /// from writer drops: a slow link must not look like a disk problem.
+    pub fn tcp_dropped_samples(&self) -> u64 {
+        self.tcp_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Taps the input for a rough live spectrum. Each accumulated frame of
+    /// `fft_size` mono-folded samples is windowed and transformed on a
+    /// worker thread — never in the audio callback — and the callback
+    /// receives `fft_size / 2` magnitude bins. Frames are dropped when the
+    /// worker falls behind; this is for monitoring, not archival.
+    pub fn set_spectrum_callback(
+        &mut self,
+        fft_size: usize,
+        window: WindowType,
+        callback: impl Fn(&[f32]) + Send + 'static,
+    ) -> Result<(), Error> {
+        if !fft_size.is_power_of_two() {
+            return Err(anyhow!("fft size must be a power of two, got {}", fft_size));
+        }
+        let (tx, rx) = mpsc::sync_channel(spectrum::QUEUE_DEPTH);
+        spectrum::spawn_worker(rx, fft_size, window, Box::new(callback));
+        self.spectrum_tx = Some(tx);
+        Ok(())
+    }
+
+    /// Registers a callback receiving a [`RecorderEvent`] whenever a file
+    /// opens or closes. Events fire for every recording entry point,
+    /// including roll-overs inside long recordings and each file of a
+    /// batch, so embedders can update dashboards or upload per file.
+    pub fn set_event_callback(&mut self, callback: impl Fn(RecorderEvent) + Send + 'static) {
+        self.event_callback = Some(Box::new(callback));
+    }
+
+    /// Registers a callback invoked for every cpal stream error, e.g. to
+    /// flash an LED or bump a health metric. It runs in cpal's error
+    /// context on the audio thread, so it must be cheap and must not
+    /// block. Errors are still logged, and device-loss handling for
+    /// auto-reconnect is unaffected.
+    pub fn set_error_callback(
+        &mut self,
+        callback: impl Fn(&cpal::StreamError) + Send + Sync + 'static,
+    ) {
+        self.error_callback = Some(Arc::new(callback));
+    }
+
+    fn emit_file_started(&self) {
+        if let (Some(callback), Some(timestamp)) = (&self.event_callback, self.file_started) {
+            callback(RecorderEvent::FileStarted {
+                path: PathBuf::from(&self.current_file),
+                timestamp,
+            });
+        }
+    }
+
+    fn emit_file_stopped(&self, samples_written: u64, checksum: Option<String>) {
+        if let Some(callback) = &self.event_callback {
+            let duration = self
+                .file_started
+                .and_then(|started| {
+                    Local::now()
+                        .signed_duration_since(started)
+                        .to_std()
+                        .ok()
+                })
+                .unwrap_or_default();
+            callback(RecorderEvent::FileStopped {
+                path: PathBuf::from(&self.current_file),
+                samples_written,
+                duration,
+                checksum,
+            });
+        }
+    }
+
+    fn init_writer(&mut self) -> Result<(), Error> {
+        self.init_writer_at(Local::now())
+    }
+
+    /// Locks the writer, surfacing a poisoned lock (the audio callback
+    /// panicked while holding it) as an error instead of aborting the
+    /// process, which would lose the whole session on an unattended
+    /// deployment.
+    #[allow(clippy::type_complexity)]
+    fn lock_writer(&self) -> Result<MutexGuard<'_, Option<WavWriter<Box<dyn WavSink>>>>, Error> {
+        self.writer
+            .lock()
+            .map_err(|_| RecorderError::Poisoned.into())
+    }
+
+    /// Writes into the supplied sink instead of opening a file on disk,
+    /// e.g. an in-memory `Cursor<Vec<u8>>` in tests or a wrapper around a
+    /// network connection. The sink replaces the file for the whole next
+    /// recording: no path is involved, so file rollover, metadata chunks,
+    /// checksums, and sidecars are all skipped. Only plain wav output
+    /// without channel splitting writes through a caller-supplied sink.
+    pub fn init_writer_into(
+        &mut self,
+        sink: impl Read + Write + Seek + Send + 'static,
+    ) -> Result<(), Error> {
+        if self.format != OutputFormat::Wav {
+            return Err(anyhow!("caller-supplied sinks require wav output"));
+        }
+        if self.split_channels {
+            return Err(anyhow!(
+                "caller-supplied sinks do not combine with channel splitting"
+            ));
+        }
+        let spec = self.get_wav_spec()?;
+        let boxed: Box<dyn WavSink> = Box::new(sink);
+        *self.lock_writer()? = Some(WavWriter::new(boxed, spec)?);
+        self.memory_sink = true;
+        Ok(())
+    }
+
+    /// Opens a new file stamped with a caller-chosen timestamp, so the
+    /// files of a multi-device session share one prefix.
+    fn init_writer_at(&mut self, started: DateTime<Local>) -> Result<(), Error> {
+        let started = self.check_clock(started)?;
+        self.samples_written.store(0, Ordering::Relaxed);
+        // A sink installed via `init_writer_into` stands in for the file;
+        // leave it in place instead of opening one.
+        if self.memory_sink {
+            self.file_started = Some(started);
+            return Ok(());
+        }
+        if let Some(path) = self.pending_path.take() {
+            log::info!("STATUS output path is now {}", path.display());
+            self.path = path;
+        }
+        if let Some(min) = self.min_free_bytes {
+            if free_bytes(&self.path)? < min {
+                return Err(anyhow!(
+                    "not enough free disk space on {} to start a new file",
+                    self.path.display()
+                ));
+            }
+        }
+        if self.append {
+            if self.format != OutputFormat::Wav || self.split_channels {
+                return Err(anyhow!("append mode only supports plain wav output"));
+            }
+            if let Some(filename) = self.find_append_target()? {
+                return self.append_writer(&filename, started);
+            }
+            // No earlier file to continue; fall through and create one.
+        }
+        let filename = self.get_filename(&started)?;
+        let spec = self.get_wav_spec()?;
+        match self.format {
+            OutputFormat::Wav if self.split_channels => {
+                if self.downmix {
+                    return Err(anyhow!("channel splitting does not combine with mono downmix"));
+                }
+                if self.target_sample_rate.is_some() {
+                    return Err(anyhow!("channel splitting does not combine with resampling"));
+                }
+                let mono = WavSpec { channels: 1, ..spec };
+                let mut writers = Vec::with_capacity(spec.channels as usize);
+                for channel in 1..=spec.channels {
+                    writers.push(WavWriter::create(split_filename(&filename, channel), mono)?);
+                }
+                *self
+                    .split_writers
+                    .lock()
+                    .map_err(|_| RecorderError::Poisoned)? = writers;
+            }
+            OutputFormat::Wav => {
+                let (writer, sync_handle) = wav_writer_create(&filename, spec)?;
+                *self.lock_writer()? = Some(writer);
+                self.sync_file = Some(sync_handle);
+            }
+            OutputFormat::Flac => {
+                if self.target_sample_rate.is_some() {
+                    return Err(anyhow!("resampling is not supported with FLAC output"));
+                }
+                let (tx, rx) = mpsc::sync_channel(flac::QUEUE_DEPTH);
+                self.flac_worker = Some(flac::spawn_worker(rx, filename.clone(), &spec)?);
+                self.encoder_tx = Some(tx);
+            }
+            OutputFormat::Opus => {
+                if self.target_sample_rate.is_some() {
+                    return Err(anyhow!("resampling is not supported with Opus output"));
+                }
+                let (tx, rx) = mpsc::sync_channel(ogg_opus::QUEUE_DEPTH);
+                self.opus_worker = Some(ogg_opus::spawn_worker(
+                    rx,
+                    filename.clone(),
+                    &spec,
+                    self.opus_bitrate,
+                )?);
+                self.encoder_tx = Some(tx);
+            }
+            OutputFormat::OggVorbis { quality } => {
+                if self.target_sample_rate.is_some() {
+                    return Err(anyhow!("resampling is not supported with Vorbis output"));
+                }
+                let (tx, rx) = mpsc::sync_channel(ogg_vorbis::QUEUE_DEPTH);
+                self.vorbis_worker = Some(ogg_vorbis::spawn_worker(
+                    rx,
+                    filename.clone(),
+                    &spec,
+                    quality,
+                )?);
+                self.encoder_tx = Some(tx);
+            }
+            OutputFormat::RawPcm { endianness } => {
+                if self.target_sample_rate.is_some() {
+                    return Err(anyhow!("resampling is not supported with raw PCM output"));
+                }
+                let (tx, rx) = mpsc::sync_channel(raw_pcm::QUEUE_DEPTH);
+                self.raw_worker = Some(raw_pcm::spawn_worker(
+                    rx,
+                    filename.clone(),
+                    &spec,
+                    endianness,
+                )?);
+                self.encoder_tx = Some(tx);
+            }
+        }
+        self.open_timeline(&filename)?;
+        self.current_file = filename;
+        self.file_started = Some(started);
+        self.emit_file_started();
+        Ok(())
+    }
+
+    /// Returns the most recent of this recorder's wav files in the output
+    /// directory — the file a restart should continue — or None when none
+    /// exists yet. With date folders enabled the scan descends into the
+    /// `YYYY/MM/DD/` subdirectories, where the newest file actually
+    /// lives.
+    fn find_append_target(&self) -> Result<Option<String>, Error> {
+        let mut newest: Option<(SystemTime, PathBuf)> = None;
+        let depth = if self.date_folders { 3 } else { 0 };
+        self.scan_append_dir(&self.path, depth, &mut newest)?;
+        Ok(newest.map(|(_, path)| path.display().to_string()))
+    }
+
+    /// One directory level of the append-target scan, recursing `depth`
+    /// more levels into subdirectories.
+    fn scan_append_dir(
+        &self,
+        dir: &Path,
+        depth: usize,
+        newest: &mut Option<(SystemTime, PathBuf)>,
+    ) -> Result<(), Error> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                if depth > 0 {
+                    self.scan_append_dir(&path, depth - 1, newest)?;
+                }
+                continue;
+            }
+            let named_ours = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&self.name) && name.ends_with(".wav"));
+            if !named_ours || !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified()?;
+            if newest.as_ref().is_none_or(|&(at, _)| modified > at) {
+                *newest = Some((modified, path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reopens `filename` and seeks past its existing data, so capture
+    /// continues the file instead of starting a new one. The existing
+    /// header must describe the same spec this recorder would write;
+    /// finalize later updates the header sizes to cover old and new
+    /// samples alike.
+    fn append_writer(&mut self, filename: &str, started: DateTime<Local>) -> Result<(), Error> {
+        let spec = self.get_wav_spec()?;
+        let (writer, sync_handle) = wav_writer_append(filename)?;
+        if writer.spec() != spec {
+            return Err(anyhow!(
+                "cannot append to {}: its spec {:?} does not match the configured {:?}",
+                filename,
+                writer.spec(),
+                spec
+            ));
+        }
+        self.samples_written
+            .store(writer.len() as u64, Ordering::Relaxed);
+        *self.lock_writer()? = Some(writer);
+        self.sync_file = Some(sync_handle);
+        self.open_timeline(filename)?;
+        self.current_file = filename.to_string();
+        self.file_started = Some(started);
+        self.emit_file_started();
+        log::info!("APPEND: {}", self.current_file);
+        Ok(())
+    }
+
+    /// Opens a new file and writes the buffered pre-trigger audio into it
+    /// before the writer becomes visible to the audio callback, so the
+    /// pre-roll ends up ahead of the live samples.
+    fn init_writer_with_pretrigger(&mut self) -> Result<(), Error> {
+        let started = self.check_clock(Local::now())?;
+        let filename = self.get_filename(&started)?;
+        let spec = self.get_wav_spec()?;
+        let (mut writer, sync_handle) = wav_writer_create(&filename, spec)?;
+        self.sync_file = Some(sync_handle);
+        let pre_roll: Vec<f32> = self
+            .pretrigger
+            .lock()
+            .map_err(|_| RecorderError::Poisoned)?
+            .drain(..)
+            .collect();
+        self.samples_written
+            .store(pre_roll.len() as u64, Ordering::Relaxed);
+        for sample in pre_roll {
+            match (spec.sample_format, spec.bits_per_sample) {
+                (hound::SampleFormat::Float, _) => writer.write_sample(sample)?,
+                (hound::SampleFormat::Int, 32) => writer.write_sample(i32::from_sample(sample))?,
+                (hound::SampleFormat::Int, 24) => {
+                    writer.write_sample((i32::from_sample(sample)) >> 8)?
+                }
+                (hound::SampleFormat::Int, 8) => writer.write_sample(i8::from_sample(sample))?,
+                (hound::SampleFormat::Int, _) => writer.write_sample(i16::from_sample(sample))?,
+            }
+        }
+        *self.lock_writer()? = Some(writer);
+        self.open_timeline(&filename)?;
+        self.current_file = filename;
+        self.file_started = Some(started);
+        self.emit_file_started();
+        Ok(())
+    }
+
+    /// Starts capture into a file stamped with the given session
+    /// timestamp. Used by `MultiRecorder` so every device of a session
+    /// shares one filename prefix.
+    pub(crate) fn start_session(&mut self, started: DateTime<Local>) -> Result<(), Error> {
+        self.init_writer_at(started)?;
+        self.start_stream()?;
+        log::info!("REC: {}", self.current_file);
+        Ok(())
+    }
+
+    /// Stops the stream, finalizes the file, and reports drop statistics,
+    /// mirroring how the single-device entry points wind down.
+    pub(crate) fn finish_session(&mut self) -> Result<(), Error> {
+        self.stop_stream();
+        self.finalize_writer()?;
+        self.report_dropped();
+        Ok(())
+    }
+
+    /// Rolls the file over when it has reached the wav size limit.
+    pub(crate) fn roll_if_needed(&mut self) -> Result<(), Error> {
+        // Rolling over means opening a fresh file, which a caller-supplied
+        // sink does not have; the caller owns its size budget.
+        if self.memory_sink {
+            return Ok(());
+        }
+        if self.writer_bytes() >= self.roll_bytes() {
+            self.roll_writer()?;
+        }
+        Ok(())
+    }
+
+    /// Finalizes the current file if one is open, appending any configured
+    /// metadata chunks to it.
+    fn finalize_writer(&mut self) -> Result<(), Error> {
+        self.set_state(RecorderState::Finalizing);
+        let writer = self.lock_writer()?.take();
+        let split = std::mem::take(
+            &mut *self
+                .split_writers
+                .lock()
+                .map_err(|_| RecorderError::Poisoned)?,
+        );
+        let finished = if !split.is_empty() {
+            let markers = self.take_markers()?;
+            let channels = split.len() as u16;
+            let mut samples_written = 0u64;
+            let mut first_error: Option<hound::Error> = None;
+            for (index, writer) in split.into_iter().enumerate() {
+                samples_written += writer.len() as u64;
+                // Keep finalizing the remaining channels on an error; one
+                // failed header must not leave its siblings with stale
+                // headers claiming zero samples.
+                if let Err(err) = writer.finalize() {
+                    log::error!("finalizing channel {} failed: {}", index + 1, err);
+                    first_error.get_or_insert(err);
+                }
+            }
+            for channel in 1..=channels {
+                self.append_metadata_chunks(&split_filename(&self.current_file, channel), &markers)?;
+            }
+            if let Some(err) = first_error {
+                return Err(err.into());
+            }
+            Some(samples_written)
+        } else if let Some(writer) = writer {
+            let samples_written = writer.len() as u64;
+            writer.finalize()?;
+            let markers = self.take_markers()?;
+            if !self.memory_sink {
+                self.append_metadata_chunks(&self.current_file, &markers)?;
+            }
+            Some(samples_written)
+        } else if let Some(worker) = self.flac_worker.take() {
+            // The callback's sender clone went away with the stream; ours
+            // goes here, closing the channel so the encoder can drain and
+            // rewrite its header.
+            self.encoder_tx = None;
+            Some(worker.finish()?)
+        } else if let Some(worker) = self.opus_worker.take() {
+            self.encoder_tx = None;
+            Some(worker.finish()?)
+        } else if let Some(worker) = self.vorbis_worker.take() {
+            self.encoder_tx = None;
+            Some(worker.finish()?)
+        } else if let Some(worker) = self.raw_worker.take() {
+            self.encoder_tx = None;
+            Some(worker.finish()?)
+        } else {
+            None
+        };
+        if let Some(samples_written) = finished {
+            self.last_rms_dbfs = self.take_loudness();
+            if let Some(rms) = self.last_rms_dbfs {
+                log::info!("integrated loudness: {:.1} dBFS RMS", rms);
+            }
+            self.last_balance = self.take_balance();
+            self.report_balance();
+            if self.memory_sink {
+                // No file exists to checksum or annotate; hand the sample
+                // count straight to the event callback and reset.
+                self.memory_sink = false;
+                self.emit_file_stopped(samples_written, None);
+                self.file_started = None;
+                self.set_state(RecorderState::Idle);
+                return Ok(());
+            }
+            self.normalize_finalized();
+            let checksum = if self.checksum {
+                Some(file_sha256(&self.current_file)?)
+            } else {
+                None
+            };
+            if self.sidecar {
+                self.write_sidecar(samples_written, checksum.as_deref())?;
+            }
+            if self.catalog_csv {
+                self.append_catalog_row(samples_written)?;
+            }
+            self.emit_file_stopped(samples_written, checksum);
+            log::info!("STOP: {}", self.current_file);
+            self.render_spectrogram();
+            // Retention failures must not fail the finalize that just
+            // succeeded; a file that could not be deleted is retried
+            // after the next one.
+            if let Err(err) = self.enforce_retention() {
+                log::error!("enforcing retention policy failed: {}", err);
+            }
+        }
+        self.close_timeline();
+        self.sync_file = None;
+        self.file_started = None;
+        self.set_state(RecorderState::Idle);
+        Ok(())
+    }
+
+    /// Deletes the oldest of this recorder's files until the configured
+    /// retention budget is met again. The file just finalized and any
+    /// file currently open are never deleted. With date folders enabled
+    /// the scan descends the `YYYY/MM/DD` levels, where the files
+    /// actually live.
+    fn enforce_retention(&self) -> Result<(), Error> {
+        let Some(policy) = self.retention else {
+            return Ok(());
+        };
+        let mut files = Vec::new();
+        let depth = if self.date_folders { 3 } else { 0 };
+        self.scan_retention_dir(&self.path, depth, &mut files)?;
+        files.sort_by_key(|&(_, modified, _)| modified);
+        let doomed: Vec<&PathBuf> = match policy {
+            RetentionPolicy::MaxAge(age) => {
+                let cutoff = std::time::SystemTime::now() - age;
+                files
+                    .iter()
+                    .take_while(|&&(_, modified, _)| modified < cutoff)
+                    .map(|(path, _, _)| path)
+                    .collect()
+            }
+            RetentionPolicy::MaxTotalBytes(budget) => {
+                let mut total: u64 = files.iter().map(|&(_, _, len)| len).sum();
+                files
+                    .iter()
+                    .take_while(|&&(_, _, len)| {
+                        let over = total > budget;
+                        total = total.saturating_sub(len);
+                        over
+                    })
+                    .map(|(path, _, _)| path)
+                    .collect()
+            }
+        };
+        for path in doomed {
+            std::fs::remove_file(path)?;
+            log::info!("retention: deleted {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// One directory level of the retention scan, recursing `depth` more
+    /// levels into subdirectories.
+    fn scan_retention_dir(
+        &self,
+        dir: &Path,
+        depth: usize,
+        files: &mut Vec<(PathBuf, SystemTime, u64)>,
+    ) -> Result<(), Error> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                if depth > 0 {
+                    self.scan_retention_dir(&path, depth - 1, files)?;
+                }
+                continue;
+            }
+            let named_ours = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&self.name));
+            let audio = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| RECORDING_EXTENSIONS.contains(&ext));
+            if !named_ours
+                || !audio
+                || path == Path::new(&self.current_file)
+                || path == self.batch_state_path()
+                || !metadata.is_file()
+            {
+                continue;
+            }
+            let modified = metadata.modified()?;
+            files.push((path, modified, metadata.len()));
+        }
+        Ok(())
+    }
+
+    /// Takes the markers queued for the file being finalized, leaving an
+    /// empty queue for the next one.
+    fn take_markers(&self) -> Result<Vec<Marker>, Error> {
+        Ok(std::mem::take(
+            &mut *self.markers.lock().map_err(|_| RecorderError::Poisoned)?,
+        ))
+    }
+
+    /// Appends the Broadcast Wave `bext` chunk to a finalized file when a
+    /// description has been set.
+    fn append_metadata_chunks(&self, path: &str, markers: &[Marker]) -> Result<(), Error> {
+        if let (Some(description), Some(started)) = (&self.description, self.file_started) {
+            let data = chunks::bext_chunk(
+                description,
+                &self.name,
+                &self.format_timestamp(&started, "%Y-%m-%d"),
+                &self.format_timestamp(&started, "%H:%M:%S"),
+            );
+            chunks::append_chunk(Path::new(path), *b"bext", &data)?;
+        }
+        if let Some(location) = self.location {
+            let data = chunks::ixml_location_chunk(location.lat, location.lon, location.depth_m);
+            chunks::append_chunk(Path::new(path), *b"iXML", &data)?;
+        }
+        if !markers.is_empty() {
+            let offsets: Vec<u64> = markers.iter().map(|marker| marker.offset).collect();
+            let labels: Vec<String> =
+                markers.iter().map(|marker| marker.label.clone()).collect();
+            chunks::append_chunk(Path::new(path), *b"cue ", &chunks::cue_chunk(&offsets))?;
+            chunks::append_chunk(Path::new(path), *b"LIST", &chunks::adtl_chunk(&labels))?;
+        }
+        if !self.info_tags.is_empty() {
+            let data = chunks::info_chunk(&self.info_tags);
+            chunks::append_chunk(Path::new(path), *b"LIST", &data)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the sidecar for the file just finalized. Runs after
+    /// `finalize` so the duration and sample count are accurate.
+    fn write_sidecar(&self, samples_written: u64, checksum: Option<&str>) -> Result<(), Error> {
+        let spec = self.get_wav_spec()?;
+        let duration_secs =
+            samples_written as f64 / spec.channels as f64 / spec.sample_rate as f64;
+        let started = self.file_started.map(|started| match self.timezone {
+            TimeZoneMode::Local => started.to_rfc3339(),
+            TimeZoneMode::Utc => started.with_timezone(&Utc).to_rfc3339(),
+        });
+        let metadata = serde_json::json!({
+            "file": self.current_file,
+            "sample_rate": spec.sample_rate,
+            "channels": spec.channels,
+            "bits_per_sample": spec.bits_per_sample,
+            "format": self.format_label(),
+            "endianness": match self.format {
+                OutputFormat::RawPcm { endianness } => Some(endianness.label()),
+                _ => None,
+            },
+            "interleaving": match self.format {
+                OutputFormat::RawPcm { .. } => Some("frames interleaved in channel order"),
+                _ => None,
+            },
+            "device": self.device.name().unwrap_or_else(|_| "unknown".to_string()),
+            "host": format!("{:?}", self.host_id),
+            "started": started,
+            // True when the session clock failed the minimum-year check
+            // and timestamps were rebased onto the fallback epoch.
+            "clock_untrusted": self.clock_untrusted,
+            "duration_secs": duration_secs,
+            "samples_written": samples_written,
+            "dropped_samples": self.dropped_samples(),
+            "measured_sample_rate": self.measured_rate,
+            "rms_dbfs": self.last_rms_dbfs,
+            "channel_balance": self.last_balance.map(|balance| serde_json::json!({
+                "correlation": balance.correlation,
+                "level_diff_db": balance.level_diff_db,
+            })),
+            // The calibration tone occupies the first `boundary_frame`
+            // frames of the file; live input starts exactly there.
+            "test_tone": self.test_tone.map(|(freq_hz, secs)| serde_json::json!({
+                "freq_hz": freq_hz,
+                "amplitude": TEST_TONE_AMPLITUDE,
+                "boundary_frame": secs * u64::from(spec.sample_rate),
+            })),
+            "sha256": checksum,
+        });
+        let path = Path::new(&self.current_file).with_extension("json");
+        std::fs::write(path, serde_json::to_string_pretty(&metadata)?)?;
+        Ok(())
+    }
+
+    /// Appends the finalized file's row to the `catalog.csv` manifest in
+    /// the output directory, writing the header first when the manifest
+    /// does not exist yet. Append-only writes keep rows from earlier runs
+    /// against the same directory intact.
+    fn append_catalog_row(&self, samples_written: u64) -> Result<(), Error> {
+        let spec = self.get_wav_spec()?;
+        let duration_secs =
+            samples_written as f64 / spec.channels as f64 / spec.sample_rate as f64;
+        let started = self
+            .file_started
+            .map(|started| match self.timezone {
+                TimeZoneMode::Local => started.to_rfc3339(),
+                TimeZoneMode::Utc => started.with_timezone(&Utc).to_rfc3339(),
+            })
+            .unwrap_or_default();
+        let path = self.path.join("catalog.csv");
+        let header_needed = !path.exists();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        if header_needed {
+            writeln!(
+                file,
+                "path,started,duration_secs,sample_rate,channels,bits_per_sample,\
+                 dropped_samples,peak"
+            )?;
+        }
+        writeln!(
+            file,
+            "{},{},{:.3},{},{},{},{},{:.6}",
+            self.current_file,
+            started,
+            duration_secs,
+            spec.sample_rate,
+            spec.channels,
+            spec.bits_per_sample,
+            self.dropped_samples(),
+            self.clipping_stats().peak
+        )?;
+        Ok(())
+    }
+
+    /// Returns the peak input level seen since the last call, resetting it.
+    fn take_peak(&self) -> f32 {
+        f32::from_bits(self.peak_level.swap(0, Ordering::Relaxed))
+    }
+
+    fn start_stream(&mut self) -> Result<(), Error> {
+        let stream = match self.create_stream() {
+            Ok(stream) => stream,
+            Err(err) if self.allow_downgrade => self.create_downgraded_stream(err)?,
+            Err(err) => return Err(err),
+        };
+        stream.play()?;
+        self.stream = Some(stream);
+        self.rate_started = Some(Instant::now());
+        self.rate_samples_start = self.total_samples.load(Ordering::Relaxed);
+        self.watchdog_samples = self.rate_samples_start;
+        self.watchdog_seen = Instant::now();
+        self.set_state(RecorderState::Recording);
+        Ok(())
+    }
+
+    /// Retries the stream at progressively lower standard rates after the
+    /// requested one failed, keeping an unattended deployment recording
+    /// at reduced bandwidth instead of not at all. Only plain wav output
+    /// can downgrade: the already-open file is reopened with the new
+    /// spec before any samples land, which encoded and split outputs
+    /// cannot do mid-worker. When no lower rate opens either, the
+    /// original error is returned.
+    fn create_downgraded_stream(&mut self, original: Error) -> Result<Stream, Error> {
+        if self.format != OutputFormat::Wav || self.split_channels || self.memory_sink {
+            return Err(original);
+        }
+        let requested = self.user_config.sample_rate.0;
+        let supported: Vec<_> = self.device.supported_input_configs()?.collect();
+        for &rate in DOWNGRADE_RATES {
+            if rate >= requested {
+                continue;
+            }
+            let fits = supported.iter().any(|range| {
+                range.channels() == self.user_config.channels
+                    && range.min_sample_rate().0 <= rate
+                    && rate <= range.max_sample_rate().0
+            });
+            if !fits {
+                continue;
+            }
+            self.user_config.sample_rate = SampleRate(rate);
+            match self.create_stream() {
+                Ok(stream) => {
+                    log::warn!(
+                        "stream at {} Hz failed ({:#}); downgraded capture to {} Hz",
+                        requested,
+                        original,
+                        rate
+                    );
+                    // The file opened just ahead of the stream still
+                    // claims the requested rate; reopen it with the
+                    // downgraded spec while it is still empty.
+                    if self.lock_writer()?.is_some() {
+                        let spec = self.get_wav_spec()?;
+                        let (writer, sync_handle) =
+                            wav_writer_create(&self.current_file, spec)?;
+                        *self.lock_writer()? = Some(writer);
+                        self.sync_file = Some(sync_handle);
+                    }
+                    return Ok(stream);
+                }
+                Err(err) => log::warn!("retry at {} Hz failed: {:#}", rate, err),
+            }
+        }
+        self.user_config.sample_rate = SampleRate(requested);
+        Err(original)
+    }
+
+    fn stop_stream(&mut self) {
+        self.stream = None;
+        // Dropping the stream abandoned the ring's producer; the writer
+        // thread drains what is queued and exits, so by the time finalize
+        // runs every captured sample has reached the writer.
+        if let Some(handle) = self.ring_thread.take() {
+            let _ = handle.join();
+        }
+        if let Some(started) = self.rate_started.take() {
+            self.recorded += started.elapsed();
+            let elapsed = started.elapsed().as_secs_f64();
+            let samples = self.total_samples.load(Ordering::Relaxed) - self.rate_samples_start;
+            let frames = samples as f64 / f64::from(self.user_config.channels);
+            if elapsed > 0.0 && frames > 0.0 {
+                self.measured_rate = Some(frames / elapsed);
+            }
+        }
+    }
+
+    /// Returns the health counters gathered since init, so embedders can
+    /// render their own shutdown report or feed a metrics pipeline.
+    pub fn session_summary(&self) -> SessionSummary {
+        SessionSummary {
+            files: self.file_index,
+            samples: self.total_samples.load(Ordering::Relaxed),
+            duration: self.recorded,
+            dropped_samples: self.dropped_samples(),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            peak: f32::from_bits(self.session_peak.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Logs a one-line session health report. Called at the end of the
+    /// long-running entry points so unattended runs leave a verdict in
+    /// the journal beyond the individual `STOP:` lines.
+    pub fn log_summary(&self) {
+        let summary = self.session_summary();
+        log::info!(
+            "session summary: {} files, {} samples over {:.1}s, {} dropped, {} reconnects, peak {:.3}",
+            summary.files,
+            summary.samples,
+            summary.duration.as_secs_f64(),
+            summary.dropped_samples,
+            summary.reconnects,
+            summary.peak
+        );
+    }
+
+    /// Returns the capture rate actually delivered by the device clock
+    /// over the last stream run, measured as frames received against
+    /// wall-clock time, or None before the first run completes. Cheap USB
+    /// interfaces drift by a few Hz from nominal, which misaligns
+    /// correlations over long deployments; this value lets users correct
+    /// timing offline. Time spent paused counts against the measurement,
+    /// so it is only meaningful for uninterrupted runs.
+    pub fn measured_sample_rate(&self) -> Option<f64> {
+        self.measured_rate
+    }
+
+    fn report_dropped(&self) {
+        let dropped = self.dropped_samples();
+        if dropped > 0 {
+            log::warn!("dropped samples: {}", dropped);
+        }
+        let reconnects = self.reconnects.load(Ordering::Relaxed);
+        if reconnects > 0 {
+            log::info!("reconnected {} times after device loss", reconnects);
+        }
+        if self.gain_clipped.load(Ordering::Relaxed) {
+            log::warn!("gain clipped samples to full scale, reduce the gain");
+        }
+        let stats = self.clipping_stats();
+        let total = self.total_samples.load(Ordering::Relaxed);
+        if total > 0 && stats.clipped_samples as f64 / total as f64 > CLIP_WARN_FRACTION {
+            log::warn!(
+                "{} of {} samples clipped (peak {:.3}), reduce input gain",
+                stats.clipped_samples,
+                total,
+                stats.peak
+            );
+        }
+    }
+
+    /// Waits until `deadline`, rolling the file over if it reaches the wav
+    /// size limit. Returns true when recording should stop early: an
+    /// interrupt arrived, disk space ran low, or sample writes failed.
+    fn wait_until(&mut self, deadline: Instant) -> Result<bool, Error> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            if self
+                .interrupt_handles
+                .stream_wait_timeout(remaining.min(SIZE_CHECK_INTERVAL))
+            {
+                return Ok(true);
+            }
+            if self.disk_low()? {
+                return Ok(true);
+            }
+            if self.write_errors() > 0 {
+                return Ok(true);
+            }
+            self.check_stream_health()?;
+            self.flush_if_due();
+            self.sync_if_due();
+            if self.writer_bytes() >= self.roll_bytes() {
+                self.roll_writer()?;
+            }
+        }
+    }
+
+    /// Rebuilds the stream after the error callback reported the device
+    /// gone, retrying with exponential backoff until it re-enumerates. The
+    /// interrupted file is finalized and recording resumes into a new
+    /// timestamped file. A no-op unless auto-reconnect is enabled and a
+    /// device loss is pending. Also runs the stall watchdog: when a
+    /// stream timeout is configured and the sample counter has not moved
+    /// for that long, the stream is declared lost even though no error
+    /// callback fired.
+    fn check_stream_health(&mut self) -> Result<(), Error> {
+        if let Some(timeout) = self.stream_timeout {
+            if self.stream.is_some() {
+                let samples = self.total_samples.load(Ordering::Relaxed);
+                if samples != self.watchdog_samples {
+                    self.watchdog_samples = samples;
+                    self.watchdog_seen = Instant::now();
+                } else if self.watchdog_seen.elapsed() >= timeout {
+                    log::warn!(
+                        "no audio for {}s, treating the stream as stalled",
+                        timeout.as_secs()
+                    );
+                    if self.auto_reconnect {
+                        self.device_lost.store(true, Ordering::SeqCst);
+                    } else {
+                        self.stop_stream();
+                        self.finalize_writer()?;
+                        return Err(anyhow!(
+                            "stream stalled: no audio for {} seconds",
+                            timeout.as_secs()
+                        ));
+                    }
+                }
+            }
+        }
+        if !self.auto_reconnect || !self.device_lost.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.stop_stream();
+        self.finalize_writer()?;
+        let mut backoff = RECONNECT_INITIAL_BACKOFF;
+        loop {
+            if self.interrupt_handles.stream_wait_timeout(backoff) {
+                return Ok(());
+            }
+            let device = get_host(self.host_id)
+                .and_then(|host| get_device(host, self.device_name.clone()));
+            match device {
+                Ok(device) => {
+                    self.default_config = get_default_config(&device)?;
+                    self.device = device;
+                    break;
+                }
+                Err(_) => backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF),
+            }
+        }
+        self.init_writer()?;
+        self.start_stream()?;
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        log::info!("REC: {}", self.current_file);
+        Ok(())
+    }
+
+    /// Checks free space on the output filesystem against the configured
+    /// threshold, latching and reporting the low-disk condition once.
+    fn disk_low(&mut self) -> Result<bool, Error> {
+        let Some(min) = self.min_free_bytes else {
+            return Ok(false);
+        };
+        if !self.low_disk && free_bytes(&self.path)? < min {
+            log::warn!("free disk space below {} bytes, stopping", min);
+            self.low_disk = true;
+        }
+        Ok(self.low_disk)
+    }
+
+    /// Finalizes the current file and opens a new one without stopping the
+    /// stream, so recordings longer than the wav size limit stay valid.
+    fn roll_writer(&mut self) -> Result<(), Error> {
+        let started = self.check_clock(Local::now())?;
+        let filename = self.get_filename(&started)?;
+        let spec = self.get_wav_spec()?;
+        let (new_writer, sync_handle) = wav_writer_create(&filename, spec)?;
+        let old_writer = self.lock_writer()?.replace(new_writer);
+        self.sync_file = Some(sync_handle);
+        self.samples_written.store(0, Ordering::Relaxed);
+        if let Some(writer) = old_writer {
+            let samples_written = writer.len() as u64;
+            writer.finalize()?;
+            let markers = self.take_markers()?;
+            self.append_metadata_chunks(&self.current_file, &markers)?;
+            self.last_rms_dbfs = self.take_loudness();
+            if let Some(rms) = self.last_rms_dbfs {
+                log::info!("integrated loudness: {:.1} dBFS RMS", rms);
+            }
+            self.last_balance = self.take_balance();
+            self.report_balance();
+            self.normalize_finalized();
+            let checksum = if self.checksum {
+                Some(file_sha256(&self.current_file)?)
+            } else {
+                None
+            };
+            if self.sidecar {
+                self.write_sidecar(samples_written, checksum.as_deref())?;
+            }
+            if self.catalog_csv {
+                self.append_catalog_row(samples_written)?;
+            }
+            self.emit_file_stopped(samples_written, checksum);
+            self.render_spectrogram();
+        }
+        log::info!("STOP: {}", self.current_file);
+        // The timeline sidecar follows the file it describes: close the
+        // finished file's sidecar and restart offsets for the new one.
+        self.close_timeline();
+        self.open_timeline(&filename)?;
+        self.current_file = filename;
+        self.file_started = Some(started);
+        self.emit_file_started();
+        log::info!("REC: {}", self.current_file);
+        Ok(())
+    }
+
+    /// Returns the number of data bytes written to the current file, or 0
+    /// when no file is open or the lock is poisoned; the latter surfaces
+    /// as an error from the finalize path instead.
+    fn writer_bytes(&self) -> u64 {
+        match self.writer.lock() {
+            Ok(guard) => match guard.as_ref() {
+                Some(writer) => writer.len() as u64 * (writer.spec().bits_per_sample as u64 / 8),
+                None => 0,
+            },
+            Err(_) => 0,
+        }
+    }
+
+    fn get_filename(&mut self, started: &DateTime<Local>) -> Result<String, Error> {
+        self.file_index += 1;
+        let directory = if self.date_folders {
+            let directory = format!(
+                "{}/{}",
+                self.path.display(),
+                self.format_timestamp(started, "%Y/%m/%d")
+            );
+            std::fs::create_dir_all(&directory)?;
+            directory
+        } else {
+            self.path.display().to_string()
+        };
+        Ok(match &self.filename_template {
+            Some(template) => {
+                let file = template
+                    .replace("{name}", &self.name)
+                    .replace("{date}", &self.format_timestamp(started, "%Y-%m-%d"))
+                    .replace("{time}", &self.format_timestamp(started, "%H-%M-%S"))
+                    .replace("{index}", &format!("{:04}", self.file_index))
+                    .replace("{ext}", self.file_extension());
+                format!("{}/{}", directory, file)
+            }
+            // Millisecond precision keeps names unique when batch segments
+            // start within the same second, which would otherwise silently
+            // overwrite the earlier file. The suffix sorts lexicographically
+            // like the rest of the timestamp.
+            None => format!(
+                "{}/{}_{}.{}",
+                directory,
+                self.name,
+                self.format_timestamp(started, "%Y-%m-%d_%H-%M-%S-%3f"),
+                self.file_extension()
+            ),
+        })
+    }
+
+    /// The sidecar's name for the container format.
+    fn format_label(&self) -> &'static str {
+        match self.format {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Flac => "flac",
+            OutputFormat::Opus => "ogg-opus",
+            OutputFormat::OggVorbis { .. } => "ogg-vorbis",
+            OutputFormat::RawPcm { .. } => "raw-pcm",
+        }
+    }
+
+    fn file_extension(&self) -> &'static str {
+        match self.format {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Flac => "flac",
+            OutputFormat::Opus => "opus",
+            OutputFormat::OggVorbis { .. } => "ogg",
+            OutputFormat::RawPcm { .. } => "raw",
+        }
+    }
+
+    fn get_wav_spec(&self) -> Result<WavSpec, Error> {
+        let (bits_per_sample, sample_format) = match self.default_config.sample_format() {
+            // Wav has no 64-bit float payload; f64 devices are stored at
+            // f32, which still exceeds any ADC's precision.
+            SampleFormat::F32 | SampleFormat::F64 => (32, hound::SampleFormat::Float),
+            SampleFormat::I32 => (24, hound::SampleFormat::Int),
+            // Wider and unsigned integer formats are stored as full-width
+            // 32-bit samples; nothing real delivers more resolution.
+            SampleFormat::I64 | SampleFormat::U32 | SampleFormat::U64 => {
+                (32, hound::SampleFormat::Int)
+            }
+            SampleFormat::I16 | SampleFormat::U16 => (16, hound::SampleFormat::Int),
+            // 8-bit wav data is unsigned on disk; hound takes i8 samples
+            // and applies the offset itself.
+            SampleFormat::I8 | SampleFormat::U8 => (8, hound::SampleFormat::Int),
+            sample_format => {
+                return Err(RecorderError::UnsupportedConfig(format!(
+                    "no wav representation for sample format '{sample_format}'"
+                ))
+                .into())
+            }
+        };
+        let mut channels = match &self.channel_selection {
+            Some(selection) => selection.len() as u16,
+            None => self.user_config.channels,
+        };
+        if self.downmix && channels > 1 {
+            channels = 1;
+        }
+        let mut sample_rate = self
+            .target_sample_rate
+            .unwrap_or(self.user_config.sample_rate.0);
+        if let Some(factor) = self.decimation {
+            sample_rate /= factor;
+        }
+        Ok(WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        })
+    }
+
+    fn create_stream(&mut self) -> Result<Stream, Error> {
+        let resample_tx = match self.target_sample_rate {
+            Some(target) if target != self.user_config.sample_rate.0 => {
+                let (tx, rx) = mpsc::sync_channel(resample::QUEUE_DEPTH);
+                resample::spawn_worker(
+                    rx,
+                    Arc::clone(&self.writer),
+                    self.get_wav_spec()?,
+                    self.user_config.sample_rate.0,
+                    Arc::clone(&self.dropped_samples),
+                    Arc::clone(&self.write_errors),
+                    Arc::clone(&self.samples_written),
+                )?;
+                Some(tx)
+            }
+            _ => None,
+        };
+        let ring = if self.lock_free && self.format == OutputFormat::Wav && !self.split_channels {
+            let capacity = self.user_config.sample_rate.0 as usize
+                * self.user_config.channels as usize
+                * RING_CAPACITY_SECS;
+            let (producer, consumer) = rtrb::RingBuffer::new(capacity);
+            self.ring_thread = Some(spawn_ring_writer(
+                Arc::clone(&self.writer),
+                self.get_wav_spec()?,
+                Arc::clone(&self.dropped_samples),
+                Arc::clone(&self.write_errors),
+                Arc::clone(&self.samples_written),
+                self.write_chunk_samples,
+                consumer,
+            ));
+            Some(Arc::new(Mutex::new(producer)))
+        } else {
+            None
+        };
+        let ctx = CallbackContext {
+            writer: Arc::clone(&self.writer),
+            dropped: Arc::clone(&self.dropped_samples),
+            write_errors: Arc::clone(&self.write_errors),
+            samples_written: Arc::clone(&self.samples_written),
+            peak: Arc::clone(&self.peak_level),
+            pretrigger: Arc::clone(&self.pretrigger),
+            gain: Arc::clone(&self.gain),
+            channel_gains: self.channel_gains.clone(),
+            gain_clipped: Arc::clone(&self.gain_clipped),
+            soft_clip: self.soft_clip,
+            dither: self.dither_source()?,
+            session_peak: Arc::clone(&self.session_peak),
+            clipped_samples: Arc::clone(&self.clipped_samples),
+            total_samples: Arc::clone(&self.total_samples),
+            channels: self.user_config.channels,
+            downmix: self.downmix,
+            selection: self.channel_selection.clone(),
+            split_writers: Arc::clone(&self.split_writers),
+            split: self.split_channels,
+            // Fresh filter state per stream, so a reconnect does not
+            // replay the tail of the previous device's signal.
+            highpass: self.highpass_hz.map(|hz| {
+                Arc::new(Mutex::new(HighPass::new(
+                    hz,
+                    self.user_config.sample_rate.0,
+                    self.user_config.channels,
+                )))
+            }),
+            decimate: self.decimation.map(|factor| {
+                Arc::new(Mutex::new(Decimator::new(
+                    factor,
+                    self.user_config.sample_rate.0,
+                    self.user_config.channels,
+                )))
+            }),
+            // A fresh tone per stream: every file opened on a new stream
+            // starts with the full calibration marker.
+            tone: self.test_tone.map(|(freq_hz, secs)| {
+                Arc::new(Mutex::new(ToneState::new(
+                    freq_hz,
+                    secs,
+                    self.user_config.sample_rate.0,
+                    self.user_config.channels,
+                )))
+            }),
+            level_tx: self.level_tx.clone(),
+            tcp_tx: self.tcp_tx.clone(),
+            tcp_dropped: Arc::clone(&self.tcp_dropped),
+            loudness_tx: self.loudness_tx.clone(),
+            balance_tx: self.balance_tx.clone(),
+            timeline_tx: self.timeline_tx.clone(),
+            buffer_cb: self
+                .buffer_cb
+                .as_ref()
+                .map(|cb| (Arc::clone(cb), self.user_config.clone())),
+            ring,
+            resample_tx,
+            encoder_tx: self.encoder_tx.clone(),
+            spectrum_tx: self.spectrum_tx.clone(),
+        };
+        let config = self.user_config.clone();
+        let err_fn = {
+            let device_lost = Arc::clone(&self.device_lost);
+            let callback = self.error_callback.clone();
+            move |err| handle_stream_error(err, &device_lost, callback.as_deref())
+        };
+        let stream = match self.default_config.sample_format() {
+            SampleFormat::F32 => self.device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &_| write_input_data::<f32, f32>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::I32 => self.device.build_input_stream(
+                &config,
+                move |data: &[i32], _: &_| write_input_data_i24(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::I16 => self.device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &_| write_input_data_i16_direct(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::I8 => self.device.build_input_stream(
+                &config,
+                move |data: &[i8], _: &_| write_input_data::<i8, i8>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::U8 => self.device.build_input_stream(
+                &config,
+                move |data: &[u8], _: &_| write_input_data::<u8, i8>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::U16 => self.device.build_input_stream(
+                &config,
+                move |data: &[u16], _: &_| write_input_data::<u16, i16>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::F64 => self.device.build_input_stream(
+                &config,
+                move |data: &[f64], _: &_| write_input_data::<f64, f32>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::I64 => self.device.build_input_stream(
+                &config,
+                move |data: &[i64], _: &_| write_input_data::<i64, i32>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::U32 => self.device.build_input_stream(
+                &config,
+                move |data: &[u32], _: &_| write_input_data::<u32, i32>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            SampleFormat::U64 => self.device.build_input_stream(
+                &config,
+                move |data: &[u64], _: &_| write_input_data::<u64, i32>(data, &ctx),
+                err_fn,
+                None,
+            )?,
+            sample_format => {
+                return Err(RecorderError::UnsupportedConfig(format!(
+                    "no capture path for sample format '{sample_format}'"
+                ))
+                .into())
+            }
+        };
+        Ok(stream)
+    }
+}
+
+fn write_input_data<T, U>(input: &[T], ctx: &CallbackContext)
+where
+    T: SizedSample,
+    U: SizedSample + hound::Sample + FromSample<T> + FromSample<f32>,
+    f32: FromSample<T>,
+{
+    // The calibration tone substitutes for the input ahead of everything
+    // else, so it rides through the same filtering, gain, and conversion
+    // as live audio and the recorded reference level reflects the whole
+    // chain.
+    if let Some(tone) = &ctx.tone {
+        let substituted = match tone.lock() {
+            Ok(mut tone) if tone.remaining > 0 => Some(
+                input
+                    .iter()
+                    .map(|&sample| {
+                        if tone.remaining > 0 {
+                            tone.next_sample()
+                        } else {
+                            f32::from_sample(sample)
+                        }
+                    })
+                    .collect::<Vec<f32>>(),
+            ),
+            _ => None,
+        };
+        if let Some(buffer) = substituted {
+            let ctx = CallbackContext {
+                tone: None,
+                ..ctx.clone()
+            };
+            write_input_data::<f32, U>(&buffer, &ctx);
+            return;
+        }
+    }
+    // Filtering happens before metering so triggers and level displays
+    // see the cleaned signal; a DC-offset input would otherwise hold a
+    // level trigger open forever.
+    if let Some(filter) = &ctx.highpass {
+        let mut buffer: Vec<f32> = input.iter().map(|&sample| f32::from_sample(sample)).collect();
+        if let Ok(mut filter) = filter.lock() {
+            filter.process(&mut buffer);
+        }
+        let ctx = CallbackContext {
+            highpass: None,
+            ..ctx.clone()
+        };
+        write_input_data::<f32, U>(&buffer, &ctx);
+        return;
+    }
+    if let Some(gains) = &ctx.channel_gains {
+        let channels = ctx.channels as usize;
+        let mut buffer: Vec<f32> = input.iter().map(|&sample| f32::from_sample(sample)).collect();
+        apply_channel_gains(&mut buffer, gains, channels, ctx);
+        let ctx = CallbackContext {
+            channel_gains: None,
+            ..ctx.clone()
+        };
+        write_input_data::<f32, U>(&buffer, &ctx);
+        return;
+    }
+    // Decimation runs after filtering and calibration gains (all at the
+    // capture rate) so metering and every downstream consumer see the
+    // decimated signal that actually reaches the file.
+    if let Some(decimator) = &ctx.decimate {
+        let buffer: Vec<f32> = input.iter().map(|&sample| f32::from_sample(sample)).collect();
+        let Ok(mut decimator) = decimator.lock() else {
+            return;
+        };
+        let decimated = decimator.process(buffer);
+        drop(decimator);
+        let ctx = CallbackContext {
+            decimate: None,
+            ..ctx.clone()
+        };
+        write_input_data::<f32, U>(&decimated, &ctx);
+        return;
+    }
+    send_timeline(ctx);
+    track_peak(input.iter().map(|&sample| f32::from_sample(sample)), ctx);
+    if let Some(tx) = &ctx.level_tx {
+        send_levels(
+            input.iter().map(|&sample| f32::from_sample(sample)),
+            ctx.channels as usize,
+            tx,
+        );
+    }
+    if let Some(tx) = &ctx.spectrum_tx {
+        send_spectrum(
+            input.iter().map(|&sample| f32::from_sample(sample)),
+            ctx.channels as usize,
+            tx,
+        );
+    }
+    if let Some(tx) = &ctx.balance_tx {
+        // A full queue only nudges the per-file statistics.
+        let buffer: Vec<f32> = input.iter().map(|&sample| f32::from_sample(sample)).collect();
+        let _ = tx.try_send(buffer);
+    }
+    if let Some((callback, config)) = &ctx.buffer_cb {
+        let buffer: Vec<f32> = input.iter().map(|&sample| f32::from_sample(sample)).collect();
+        callback(&buffer, config);
+    }
+    let gain = f32::from_bits(ctx.gain.load(Ordering::Relaxed));
+    let channels = ctx.channels as usize;
+    if let Some(tx) = &ctx.tcp_tx {
+        let buffer = collect_processed(input.iter().map(|&sample| f32::from_sample(sample)), ctx, gain);
+        if tx.try_send(buffer).is_err() {
+            ctx.tcp_dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+        }
+    }
+    if let Some(tx) = &ctx.loudness_tx {
+        let buffer = collect_processed(input.iter().map(|&sample| f32::from_sample(sample)), ctx, gain);
+        // A full queue only skews the per-file figure marginally.
+        let _ = tx.try_send(buffer);
+    }
+    if let Some(tx) = ctx.encoder_tx.as_ref().or(ctx.resample_tx.as_ref()) {
+        let buffer = collect_processed(input.iter().map(|&sample| f32::from_sample(sample)), ctx, gain);
+        if tx.try_send(buffer).is_err() {
+            ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+        }
+        return;
+    }
+    if let Some(ring) = &ctx.ring {
+        let buffer = collect_processed(input.iter().map(|&sample| f32::from_sample(sample)), ctx, gain);
+        // Pushing never blocks; only a full ring (the writer thread has
+        // fallen whole seconds behind) loses samples.
+        if let Ok(mut producer) = ring.lock() {
+            for sample in buffer {
+                if producer.push(sample).is_err() {
+                    ctx.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        return;
+    }
+    if ctx.split {
+        if let Ok(mut guard) = ctx.split_writers.try_lock() {
+            let mut written = 0u64;
+            write_split_frames(input, ctx, &mut |slot, mut sample| {
+                if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+                    sample = apply_gain(sample, gain, ctx);
+                }
+                if let Some(writer) = guard.get_mut(slot) {
+                    if writer.write_sample(U::from_sample(sample)).is_err() {
+                        record_write_error(ctx);
+                    } else {
+                        written += 1;
+                    }
+                }
+            });
+            ctx.samples_written.fetch_add(written, Ordering::Relaxed);
+        } else {
+            ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+        }
+        return;
+    }
+    if let Ok(mut guard) = ctx.writer.try_lock() {
+        match guard.as_mut() {
+            Some(writer) => {
+                let mut written = 0u64;
+                if ctx.selection.is_some() || (ctx.downmix && channels > 1) {
+                    let mut write = |mut sample: f32| {
+                        if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+                            sample = apply_gain(sample, gain, ctx);
+                        }
+                        if writer.write_sample(U::from_sample(sample)).is_err() {
+                            record_write_error(ctx);
+                        } else {
+                            written += 1;
+                        }
+                    };
+                    for frame in input.chunks_exact(channels) {
+                        write_frame(frame, ctx, &mut write);
+                    }
+                } else {
+                    for &sample in input.iter() {
+                        let sample: U = if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+                            U::from_sample(apply_gain(f32::from_sample(sample), gain, ctx))
+                        } else {
+                            U::from_sample(sample)
+                        };
+                        if writer.write_sample(sample).is_err() {
+                            record_write_error(ctx);
+                        } else {
+                            written += 1;
+                        }
+                    }
+                }
+                ctx.samples_written.fetch_add(written, Ordering::Relaxed);
+            }
+            None => push_pretrigger(
+                input.iter().map(|&sample| f32::from_sample(sample)),
+                &ctx.pretrigger,
+            ),
+        }
+    } else {
+        ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Bulk fast path for the common case where the device already delivers
+/// what the file stores: 16-bit input, 16-bit wav, and nothing configured
+/// that needs the samples touched. Metering still runs, but the samples
+/// go to hound's buffered 16-bit writer in one batch instead of through a
+/// per-sample conversion, which measurably cuts CPU on constrained
+/// hardware at high rates. Any configured processing falls back to the
+/// generic path.
+fn write_input_data_i16_direct(input: &[i16], ctx: &CallbackContext) {
+    let gain = f32::from_bits(ctx.gain.load(Ordering::Relaxed));
+    let fast_eligible = ctx.highpass.is_none()
+        && ctx.decimate.is_none()
+        && ctx.tone.is_none()
+        && gain == 1.0
+        && !ctx.soft_clip
+        && ctx.dither.is_none()
+        && ctx.channel_gains.is_none()
+        && ctx.selection.is_none()
+        && !ctx.downmix
+        && !ctx.split
+        && ctx.ring.is_none()
+        && ctx.resample_tx.is_none()
+        && ctx.encoder_tx.is_none()
+        && ctx.tcp_tx.is_none()
+        && ctx.loudness_tx.is_none()
+        && ctx.buffer_cb.is_none();
+    if !fast_eligible {
+        write_input_data::<i16, i16>(input, ctx);
+        return;
+    }
+    send_timeline(ctx);
+    track_peak(input.iter().map(|&sample| f32::from_sample(sample)), ctx);
+    if let Some(tx) = &ctx.level_tx {
+        send_levels(
+            input.iter().map(|&sample| f32::from_sample(sample)),
+            ctx.channels as usize,
+            tx,
+        );
+    }
+    if let Some(tx) = &ctx.spectrum_tx {
+        send_spectrum(
+            input.iter().map(|&sample| f32::from_sample(sample)),
+            ctx.channels as usize,
+            tx,
+        );
+    }
+    if let Some(tx) = &ctx.balance_tx {
+        // A full queue only nudges the per-file statistics.
+        let buffer: Vec<f32> = input.iter().map(|&sample| f32::from_sample(sample)).collect();
+        let _ = tx.try_send(buffer);
+    }
+    if let Ok(mut guard) = ctx.writer.try_lock() {
+        match guard.as_mut() {
+            Some(writer) => {
+                let mut fast = writer.get_i16_writer(input.len() as u32);
+                for &sample in input {
+                    fast.write_sample(sample);
+                }
+                if fast.flush().is_err() {
+                    record_write_failures(&ctx.dropped, &ctx.write_errors, input.len() as u64);
+                } else {
+                    ctx.samples_written
+                        .fetch_add(input.len() as u64, Ordering::Relaxed);
+                }
+            }
+            None => push_pretrigger(
+                input.iter().map(|&sample| f32::from_sample(sample)),
+                &ctx.pretrigger,
+            ),
+        }
+    } else {
+        ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Writes 32-bit integer input as 24-bit samples. cpal delivers 24-bit ADC
+/// data left-justified in an i32, so the low-order padding byte is dropped
+/// to pack the sample into the 24 bits declared in the wav spec.
+fn write_input_data_i24(input: &[i32], ctx: &CallbackContext) {
+    if let Some(tone) = &ctx.tone {
+        let substituted = match tone.lock() {
+            Ok(mut tone) if tone.remaining > 0 => Some(
+                input
+                    .iter()
+                    .map(|&sample| {
+                        if tone.remaining > 0 {
+                            i32::from_sample(tone.next_sample())
+                        } else {
+                            sample
+                        }
+                    })
+                    .collect::<Vec<i32>>(),
+            ),
+            _ => None,
+        };
+        if let Some(buffer) = substituted {
+            let ctx = CallbackContext {
+                tone: None,
+                ..ctx.clone()
+            };
+            write_input_data_i24(&buffer, &ctx);
+            return;
+        }
+    }
+    if let Some(filter) = &ctx.highpass {
+        let mut buffer: Vec<f32> = input
+            .iter()
+            .map(|&sample| sample as f32 / i32::MAX as f32)
+            .collect();
+        if let Ok(mut filter) = filter.lock() {
+            filter.process(&mut buffer);
+        }
+        let filtered: Vec<i32> = buffer
+            .into_iter()
+            .map(|sample| i32::from_sample(sample.clamp(-1.0, 1.0)))
+            .collect();
+        let ctx = CallbackContext {
+            highpass: None,
+            ..ctx.clone()
+        };
+        write_input_data_i24(&filtered, &ctx);
+        return;
+    }
+    if let Some(gains) = &ctx.channel_gains {
+        let channels = ctx.channels as usize;
+        let mut buffer: Vec<f32> = input
+            .iter()
+            .map(|&sample| sample as f32 / i32::MAX as f32)
+            .collect();
+        apply_channel_gains(&mut buffer, gains, channels, ctx);
+        let balanced: Vec<i32> = buffer
+            .into_iter()
+            .map(|sample| i32::from_sample(sample.clamp(-1.0, 1.0)))
+            .collect();
+        let ctx = CallbackContext {
+            channel_gains: None,
+            ..ctx.clone()
+        };
+        write_input_data_i24(&balanced, &ctx);
+        return;
+    }
+    if let Some(decimator) = &ctx.decimate {
+        let buffer: Vec<f32> = input
+            .iter()
+            .map(|&sample| sample as f32 / i32::MAX as f32)
+            .collect();
+        let Ok(mut decimator) = decimator.lock() else {
+            return;
+        };
+        let decimated: Vec<i32> = decimator
+            .process(buffer)
+            .into_iter()
+            .map(|sample| i32::from_sample(sample.clamp(-1.0, 1.0)))
+            .collect();
+        drop(decimator);
+        let ctx = CallbackContext {
+            decimate: None,
+            ..ctx.clone()
+        };
+        write_input_data_i24(&decimated, &ctx);
+        return;
+    }
+    send_timeline(ctx);
+    track_peak(
+        input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+        ctx,
+    );
+    if let Some(tx) = &ctx.level_tx {
+        send_levels(
+            input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+            ctx.channels as usize,
+            tx,
+        );
+    }
+    if let Some(tx) = &ctx.spectrum_tx {
+        send_spectrum(
+            input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+            ctx.channels as usize,
+            tx,
+        );
+    }
+    if let Some(tx) = &ctx.balance_tx {
+        // A full queue only nudges the per-file statistics.
+        let buffer: Vec<f32> = input
+            .iter()
+            .map(|&sample| sample as f32 / i32::MAX as f32)
+            .collect();
+        let _ = tx.try_send(buffer);
+    }
+    if let Some((callback, config)) = &ctx.buffer_cb {
+        let buffer: Vec<f32> = input
+            .iter()
+            .map(|&sample| sample as f32 / i32::MAX as f32)
+            .collect();
+        callback(&buffer, config);
+    }
+    let gain = f32::from_bits(ctx.gain.load(Ordering::Relaxed));
+    let channels = ctx.channels as usize;
+    if let Some(tx) = &ctx.tcp_tx {
+        let buffer = collect_processed(
+            input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+            ctx,
+            gain,
+        );
+        if tx.try_send(buffer).is_err() {
+            ctx.tcp_dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+        }
+    }
+    if let Some(tx) = &ctx.loudness_tx {
+        let buffer = collect_processed(
+            input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+            ctx,
+            gain,
+        );
+        let _ = tx.try_send(buffer);
+    }
+    if let Some(tx) = ctx.encoder_tx.as_ref().or(ctx.resample_tx.as_ref()) {
+        let buffer = collect_processed(
+            input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+            ctx,
+            gain,
+        );
+        if tx.try_send(buffer).is_err() {
+            ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+        }
+        return;
+    }
+    if let Some(ring) = &ctx.ring {
+        let buffer = collect_processed(
+            input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+            ctx,
+            gain,
+        );
+        if let Ok(mut producer) = ring.lock() {
+            for sample in buffer {
+                if producer.push(sample).is_err() {
+                    ctx.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        return;
+    }
+    if ctx.split {
+        if let Ok(mut guard) = ctx.split_writers.try_lock() {
+            let mut written = 0u64;
+            write_split_frames(input, ctx, &mut |slot, mut sample| {
+                if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+                    sample = apply_gain(sample, gain, ctx);
+                }
+                if let Some(writer) = guard.get_mut(slot) {
+                    if writer.write_sample(i32::from_sample(sample) >> 8).is_err() {
+                        record_write_error(ctx);
+                    } else {
+                        written += 1;
+                    }
+                }
+            });
+            ctx.samples_written.fetch_add(written, Ordering::Relaxed);
+        } else {
+            ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+        }
+        return;
+    }
+    if let Ok(mut guard) = ctx.writer.try_lock() {
+        match guard.as_mut() {
+            Some(writer) => {
+                let mut written = 0u64;
+                if ctx.selection.is_some() || (ctx.downmix && channels > 1) {
+                    let mut write = |mut sample: f32| {
+                        if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+                            sample = apply_gain(sample, gain, ctx);
+                        }
+                        if writer.write_sample(i32::from_sample(sample) >> 8).is_err() {
+                            record_write_error(ctx);
+                        } else {
+                            written += 1;
+                        }
+                    };
+                    for frame in input.chunks_exact(channels) {
+                        write_frame(frame, ctx, &mut write);
+                    }
+                } else {
+                    for &sample in input.iter() {
+                        let sample = if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+                            i32::from_sample(apply_gain(sample as f32 / i32::MAX as f32, gain, ctx))
+                        } else {
+                            sample
+                        };
+                        if writer.write_sample(sample >> 8).is_err() {
+                            record_write_error(ctx);
+                        } else {
+                            written += 1;
+                        }
+                    }
+                }
+                ctx.samples_written.fetch_add(written, Ordering::Relaxed);
+            }
+            None => push_pretrigger(
+                input.iter().map(|&sample| sample as f32 / i32::MAX as f32),
+                &ctx.pretrigger,
+            ),
+        }
+    } else {
+        ctx.dropped.fetch_add(input.len() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Runs one callback buffer through the gain, channel selection, and
+/// downmix processing and collects the result as normalized f32 samples
+/// for the resampling worker.
+fn collect_processed(
+    samples: impl Iterator<Item = f32>,
+    ctx: &CallbackContext,
+    gain: f32,
+) -> Vec<f32> {
+    let input: Vec<f32> = samples.collect();
+    let channels = ctx.channels as usize;
+    let mut out = Vec::with_capacity(input.len());
+    let mut write = |mut sample: f32| {
+        if gain != 1.0 || ctx.soft_clip || ctx.dither.is_some() {
+            sample = apply_gain(sample, gain, ctx);
+        }
+        out.push(sample);
+    };
+    if ctx.selection.is_some() || (ctx.downmix && channels > 1) {
+        for frame in input.chunks_exact(channels) {
+            write_frame(frame, ctx, &mut write);
+        }
+    } else {
+        for &sample in &input {
+            write(sample);
+        }
+    }
+    out
+}
+
+/// Routes one interleaved input frame through the channel selection and
+/// mono downmix processing, handing each output sample to `write`. Frame
+/// boundaries are preserved: selected channels are emitted in selection
+/// order and a downmix averages only the channels that survive selection.
+fn write_frame<T>(frame: &[T], ctx: &CallbackContext, write: &mut impl FnMut(f32))
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    match ctx.selection.as_deref() {
+        Some(selection) if ctx.downmix && selection.len() > 1 => {
+            let mono = selection
+                .iter()
+                .map(|&channel| f32::from_sample(frame[channel as usize]))
+                .sum::<f32>()
+                / selection.len() as f32;
+            write(mono);
+        }
+        Some(selection) => {
+            for &channel in selection {
+                write(f32::from_sample(frame[channel as usize]));
+            }
+        }
+        None => {
+            let mono = frame
+                .iter()
+                .map(|&sample| f32::from_sample(sample))
+                .sum::<f32>()
+                / frame.len() as f32;
+            write(mono);
+        }
+    }
+}
+
+/// Hands each output sample of every interleaved frame to `write` with
+/// its output channel index, honoring any channel selection, so the
+/// per-channel writers receive de-interleaved streams in output order.
+fn write_split_frames<T>(input: &[T], ctx: &CallbackContext, write: &mut impl FnMut(usize, f32))
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = ctx.channels as usize;
+    for frame in input.chunks_exact(channels) {
+        match ctx.selection.as_deref() {
+            Some(selection) => {
+                for (slot, &channel) in selection.iter().enumerate() {
+                    write(slot, f32::from_sample(frame[channel as usize]));
+                }
+            }
+            None => {
+                for (slot, &sample) in frame.iter().enumerate() {
+                    write(slot, f32::from_sample(sample));
+                }
+            }
+        }
+    }
+}
+
+/// Computes per-channel RMS and peak levels for one interleaved buffer and
+/// queues them for the metering callback, dropping the reading if the
+/// queue is full.
+fn send_levels(samples: impl Iterator<Item = f32>, channels: usize, tx: &SyncSender<LevelInfo>) {
+    let mut sum_squares = vec![0.0f64; channels];
+    let mut peak = vec![0.0f32; channels];
+    let mut frames = 0usize;
+    for (index, sample) in samples.enumerate() {
+        let channel = index % channels;
+        sum_squares[channel] += f64::from(sample) * f64::from(sample);
+        peak[channel] = peak[channel].max(sample.abs());
+        if channel == channels - 1 {
+            frames += 1;
+        }
+    }
+    if frames == 0 {
+        return;
+    }
+    let rms = sum_squares
+        .iter()
+        .map(|sum| (sum / frames as f64).sqrt() as f32)
+        .collect();
+    tx.try_send(LevelInfo { rms, peak }).ok();
+}
+
+/// Folds one interleaved buffer down to mono and queues it for the
+/// spectrum worker, dropping the buffer if the queue is full.
+fn send_spectrum(samples: impl Iterator<Item = f32>, channels: usize, tx: &SyncSender<Vec<f32>>) {
+    let mut mono = Vec::new();
+    let mut sum = 0.0f32;
+    let mut filled = 0usize;
+    for sample in samples {
+        sum += sample;
+        filled += 1;
+        if filled == channels {
+            mono.push(sum / channels as f32);
+            sum = 0.0;
+            filled = 0;
+        }
+    }
+    tx.try_send(mono).ok();
+}
+
+/// Applies the per-channel calibration gains to one interleaved buffer in
+/// place, clipping at full scale with the shared clip flag set.
+fn apply_channel_gains(buffer: &mut [f32], gains: &[f32], channels: usize, ctx: &CallbackContext) {
+    for (index, sample) in buffer.iter_mut().enumerate() {
+        let amplified = *sample * gains[index % channels];
+        let clamped = amplified.clamp(-1.0, 1.0);
+        if clamped != amplified {
+            ctx.gain_clipped.store(true, Ordering::Relaxed);
+        }
+        *sample = clamped;
+    }
+}
+
+/// Amplifies a normalized sample, clamping to full scale so integer
+/// conversions cannot wrap around. With soft clipping enabled the hard
+/// clamp is replaced by [`soft_clip`]; either way the shared clip flag is
+/// set when the amplified sample exceeded full scale. Dither, when armed
+/// for this stream, lands last so its noise is not clipped away.
+fn apply_gain(sample: f32, gain: f32, ctx: &CallbackContext) -> f32 {
+    let amplified = sample * gain;
+    if amplified.abs() > 1.0 {
+        ctx.gain_clipped.store(true, Ordering::Relaxed);
+    }
+    let limited = if ctx.soft_clip {
+        soft_clip(amplified)
+    } else {
+        amplified.clamp(-1.0, 1.0)
+    };
+    match &ctx.dither {
+        Some(dither) => dither.apply(limited),
+        None => limited,
+    }
+}
+
+/// Cubic soft clipper: linear-ish around zero, saturating smoothly into
+/// ±1.0 at ±1.5 and flat beyond, so transients that would hard-clip land
+/// on a rounded shoulder instead of a square edge. The curve does shave a
+/// little off samples approaching full scale — the usual limiter trade
+/// for keeping the harsh clipping harmonics out of spectral analysis.
+fn soft_clip(sample: f32) -> f32 {
+    let x = sample.clamp(-1.5, 1.5);
+    x - x * x * x * (4.0 / 27.0)
+}
+
+/// Retains the most recent samples in the fixed-capacity pre-trigger ring.
+/// The ring is drained into the start of the next triggered file. Pushes
+/// never allocate: the oldest sample is popped once the ring is full.
+fn push_pretrigger(samples: impl Iterator<Item = f32>, ring: &Mutex<VecDeque<f32>>) {
+    if let Ok(mut ring) = ring.try_lock() {
+        if ring.capacity() == 0 {
+            return;
+        }
+        for sample in samples {
+            if ring.len() == ring.capacity() {
+                ring.pop_front();
+            }
+            ring.push_back(sample);
+        }
+    }
+}
+
+/// Records `samples` failed sample writes against the shared counters:
+/// dropped for the session statistics, and write errors so the health
+/// checks stop the recording and surface the failure. Busy-writer losses
+/// bump `dropped` directly instead; they are expected under load and
+/// must not stop the session. The first failure is logged from here —
+/// later ones would only repeat the same story at callback rate.
+pub(crate) fn record_write_failures(
+    dropped: &AtomicU64,
+    write_errors: &AtomicU64,
+    samples: u64,
+) {
+    dropped.fetch_add(samples, Ordering::Relaxed);
+    if write_errors.fetch_add(samples, Ordering::Relaxed) == 0 {
+        log::error!("sample write failed; stopping at the next health check");
+    }
+}
+
+fn record_write_error(ctx: &CallbackContext) {
+    record_write_failures(&ctx.dropped, &ctx.write_errors, 1);
+}
+
+/// Queues the arrival time of the current buffer for the timeline
+/// sidecar. Runs ahead of the sample-count update in [`track_peak`], so
+/// the recorded offset names the buffer's first sample.
+fn send_timeline(ctx: &CallbackContext) {
+    if let Some(tx) = &ctx.timeline_tx {
+        // A full queue loses this row; it never blocks the callback.
+        let _ = tx.try_send(TimelineEntry {
+            samples: ctx.total_samples.load(Ordering::Relaxed),
+            mono: Instant::now(),
+            wall: SystemTime::now(),
+        });
+    }
+}
+
+/// Records the buffer's peak level and clipping statistics. Non-negative
+/// f32 bit patterns order like the floats themselves, so maxima can be
+/// kept with a plain atomic fetch_max.
+fn track_peak(samples: impl Iterator<Item = f32>, ctx: &CallbackContext) {
+    let mut buffer_peak = 0.0f32;
+    let mut clipped = 0u64;
+    let mut total = 0u64;
+    for sample in samples {
+        let level = sample.abs();
+        buffer_peak = buffer_peak.max(level);
+        if level >= CLIP_THRESHOLD {
+            clipped += 1;
+        }
+        total += 1;
+    }
+    ctx.peak.fetch_max(buffer_peak.to_bits(), Ordering::Relaxed);
+    ctx.session_peak
+        .fetch_max(buffer_peak.to_bits(), Ordering::Relaxed);
+    if clipped > 0 {
+        ctx.clipped_samples.fetch_add(clipped, Ordering::Relaxed);
+    }
+    ctx.total_samples.fetch_add(total, Ordering::Relaxed);
+}
+
+/// Returns the free bytes available to unprivileged users on the
+/// filesystem containing `path`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field widths vary per platform
+pub(crate) fn free_bytes(path: &Path) -> Result<u64, Error> {
+    use std::os::unix::ffi::OsStrExt;
+    let cpath = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(cpath.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn free_bytes(_path: &Path) -> Result<u64, Error> {
+    Ok(u64::MAX)
+}
+
+/// Hex SHA-256 of a finalized file. One streamed pass; hashing during the
+/// write would mean wrapping hound's writer for little gain at our file
+/// sizes.
+fn file_sha256(path: &str) -> Result<String, Error> {
+    use sha2::{Digest, Sha256};
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Builds the fixed-size header announcing the stream layout to a TCP
+/// listener. The payload that follows is always f32 samples, so bits per
+/// sample is a constant 32 regardless of the file format on disk.
+fn tcp_header(spec: &WavSpec) -> [u8; 12] {
+    let mut header = [0u8; 12];
+    header[..4].copy_from_slice(b"HPCM");
+    header[4..8].copy_from_slice(&spec.sample_rate.to_le_bytes());
+    header[8..10].copy_from_slice(&spec.channels.to_le_bytes());
+    header[10..12].copy_from_slice(&32u16.to_le_bytes());
+    header
+}
+
+/// Spawns the thread that drains the lock-free ring into the shared
+/// writer. Unlike the audio callback this thread may block on the writer
+/// lock, so a finalize in progress delays it rather than costing samples.
+/// It exits once the producer is gone and the ring is empty.
+fn spawn_ring_writer(
+    writer: WriteHandle,
+    spec: WavSpec,
+    dropped: Arc<AtomicU64>,
+    write_errors: Arc<AtomicU64>,
+    samples_written: Arc<AtomicU64>,
+    chunk_samples: usize,
+    mut consumer: rtrb::Consumer<f32>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        if consumer.is_empty() {
+            if consumer.is_abandoned() {
+                return;
+            }
+            thread::sleep(RING_POLL);
+            continue;
+        }
+        let Ok(mut guard) = writer.lock() else {
+            return;
+        };
+        let Some(open) = guard.as_mut() else {
+            // Between files the samples wait in the ring; once the
+            // producer is gone no writer is coming back for them.
+            drop(guard);
+            if consumer.is_abandoned() {
+                dropped.fetch_add(consumer.slots() as u64, Ordering::Relaxed);
+                return;
+            }
+            thread::sleep(RING_POLL);
+            continue;
+        };
+        // Drain a bounded chunk per lock acquisition: one acquisition
+        // covers many samples, while a finalize waiting on the lock
+        // still gets it after at most `chunk_samples` writes.
+        let want = consumer.slots().min(chunk_samples);
+        let Ok(chunk) = consumer.read_chunk(want) else {
+            continue;
+        };
+        let mut landed = 0u64;
+        for sample in chunk {
+            let written = match (spec.sample_format, spec.bits_per_sample) {
+                (hound::SampleFormat::Float, _) => open.write_sample(sample),
+                (hound::SampleFormat::Int, 32) => open.write_sample(i32::from_sample(sample)),
+                (hound::SampleFormat::Int, 24) => open.write_sample(i32::from_sample(sample) >> 8),
+                (hound::SampleFormat::Int, 8) => open.write_sample(i8::from_sample(sample)),
+                (hound::SampleFormat::Int, _) => open.write_sample(i16::from_sample(sample)),
+            };
+            if written.is_err() {
+                record_write_failures(&dropped, &write_errors, 1);
+            } else {
+                landed += 1;
+            }
+        }
+        samples_written.fetch_add(landed, Ordering::Relaxed);
+    })
+}
+
+/// Opens a buffered wav writer on a new file at `path`, boxed as the
+/// trait-object sink the shared [`WriteHandle`] carries. Also returns a
+/// second handle to the same open file so the durability path can fsync
+/// it without reaching through the writer.
+#[allow(clippy::type_complexity)]
+fn wav_writer_create(
+    path: &str,
+    spec: WavSpec,
+) -> Result<(WavWriter<Box<dyn WavSink>>, File), Error> {
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    let sync_handle = file.try_clone()?;
+    let sink: Box<dyn WavSink> = Box::new(BufferedFile(BufWriter::new(file)));
+    Ok((WavWriter::new(sink, spec)?, sync_handle))
+}
+
+/// Opens an existing wav file for appending. hound re-reads the spec
+/// from the header and seeks past the existing data, and finalizing the
+/// returned writer rewrites the header sizes to cover old and new
+/// samples alike.
+#[allow(clippy::type_complexity)]
+fn wav_writer_append(path: &str) -> Result<(WavWriter<Box<dyn WavSink>>, File), Error> {
+    let file = File::options().read(true).write(true).open(path)?;
+    let sync_handle = file.try_clone()?;
+    let sink: Box<dyn WavSink> = Box::new(BufferedFile(BufWriter::new(file)));
+    Ok((WavWriter::new_append(sink)?, sync_handle))
+}
+
+/// Inserts a `_chN` suffix before the extension of a generated filename,
+/// so the per-channel files of a split recording sort next to each other.
+fn split_filename(filename: &str, channel: u16) -> String {
+    match filename.rfind('.') {
+        Some(dot) => format!("{}_ch{}{}", &filename[..dot], channel, &filename[dot..]),
+        None => format!("{}_ch{}", filename, channel),
+    }
+}
+
+/// Placeholders accepted by `set_filename_template`.
+const TEMPLATE_PLACEHOLDERS: [&str; 5] = ["name", "date", "time", "index", "ext"];
+
+/// Checks a filename template for balanced braces and known placeholders.
+fn validate_template(template: &str) -> Result<(), Error> {
+    let mut key: Option<String> = None;
+    for c in template.chars() {
+        match (c, &mut key) {
+            ('{', None) => key = Some(String::new()),
+            ('{', Some(_)) => return Err(anyhow!("nested '{{' in filename template")),
+            ('}', Some(k)) => {
+                if !TEMPLATE_PLACEHOLDERS.contains(&k.as_str()) {
+                    return Err(anyhow!(
+                        "unknown placeholder '{{{}}}' in filename template, supported: {}",
+                        k,
+                        TEMPLATE_PLACEHOLDERS
+                            .map(|p| format!("{{{}}}", p))
+                            .join(", ")
+                    ));
+                }
+                key = None;
+            }
+            ('}', None) => return Err(anyhow!("unmatched '}}' in filename template")),
+            (c, Some(k)) => k.push(c),
+            (_, None) => {}
+        }
+    }
+    if key.is_some() {
+        return Err(anyhow!("unclosed '{{' in filename template"));
+    }
+    Ok(())
+}
+
+/// Reports a stream error, flags a lost device so the control thread can
+/// attempt a reconnect, and forwards the error to the user callback when
+/// one is registered.
+fn handle_stream_error(
+    err: cpal::StreamError,
+    device_lost: &AtomicBool,
+    callback: Option<&(dyn Fn(&cpal::StreamError) + Send + Sync)>,
+) {
+    if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+        device_lost.store(true, Ordering::SeqCst);
+    }
+    log::error!("an error occurred on stream: {}", err);
+    if let Some(callback) = callback {
+        callback(&err);
+    }
+}
+
+/// Scaffolding for the criterion write-path benchmarks, which need to
+/// drive `write_input_data` without audio hardware. Hidden because the
+/// callback internals it wraps are not API and may change freely.
+#[doc(hidden)]
+pub mod bench {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    /// Distinguishes the scratch files of concurrently running harnesses.
+    static HARNESS_ID: AtomicUsize = AtomicUsize::new(0);
+
+    /// Feeds synthetic f32 buffers through the real callback write path
+    /// into a 16-bit wav file in the system temp directory, over either
+    /// the mutex path or the lock-free ring.
+    pub struct WriteHarness {
+        ctx: CallbackContext,
+        ring_thread: Option<thread::JoinHandle<()>>,
+        path: PathBuf,
+    }
+
+    impl WriteHarness {
+        pub fn new(sample_rate: u32, channels: u16, lock_free: bool) -> Self {
+            let spec = WavSpec {
+                channels,
+                sample_rate,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let path = std::env::temp_dir().join(format!(
+                "write-harness-{}-{}.wav",
+                std::process::id(),
+                HARNESS_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            let (writer, _sync) =
+                wav_writer_create(path.to_str().expect("temp path is utf-8"), spec)
+                    .expect("creating harness writer");
+            let writer = Arc::new(Mutex::new(Some(writer)));
+            let dropped = Arc::new(AtomicU64::new(0));
+            let write_errors = Arc::new(AtomicU64::new(0));
+            let samples_written = Arc::new(AtomicU64::new(0));
+            let mut ring = None;
+            let mut ring_thread = None;
+            if lock_free {
+                let capacity =
+                    sample_rate as usize * channels as usize * RING_CAPACITY_SECS;
+                let (producer, consumer) = rtrb::RingBuffer::new(capacity);
+                ring_thread = Some(spawn_ring_writer(
+                    Arc::clone(&writer),
+                    spec,
+                    Arc::clone(&dropped),
+                    Arc::clone(&write_errors),
+                    Arc::clone(&samples_written),
+                    DEFAULT_WRITE_CHUNK_SAMPLES,
+                    consumer,
+                ));
+                ring = Some(Arc::new(Mutex::new(producer)));
+            }
+            let ctx = CallbackContext {
+                writer,
+                dropped,
+                write_errors,
+                samples_written,
+                peak: Arc::new(AtomicU32::new(0)),
+                pretrigger: Arc::new(Mutex::new(VecDeque::new())),
+                gain: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+                channel_gains: None,
+                gain_clipped: Arc::new(AtomicBool::new(false)),
+                soft_clip: false,
+                dither: None,
+                session_peak: Arc::new(AtomicU32::new(0)),
+                clipped_samples: Arc::new(AtomicU64::new(0)),
+                total_samples: Arc::new(AtomicU64::new(0)),
+                channels,
+                downmix: false,
+                selection: None,
+                split_writers: Arc::new(Mutex::new(Vec::new())),
+                split: false,
+                highpass: None,
+                decimate: None,
+                tone: None,
+                level_tx: None,
+                resample_tx: None,
+                encoder_tx: None,
+                spectrum_tx: None,
+                tcp_tx: None,
+                tcp_dropped: Arc::new(AtomicU64::new(0)),
+                loudness_tx: None,
+                balance_tx: None,
+                timeline_tx: None,
+                buffer_cb: None,
+                ring,
+            };
+            WriteHarness {
+                ctx,
+                ring_thread,
+                path,
+            }
+        }
+
+        /// One callback's worth of interleaved input.
+        pub fn feed(&self, buffer: &[f32]) {
+            write_input_data::<f32, i16>(buffer, &self.ctx);
+        }
+
+        /// Finalizes the file, deletes it, and returns the number of
+        /// samples that reached the writer and the number dropped.
+        pub fn finish(self) -> (u64, u64) {
+            let WriteHarness {
+                ctx,
+                ring_thread,
+                path,
+            } = self;
+            let writer = Arc::clone(&ctx.writer);
+            let dropped = Arc::clone(&ctx.dropped);
+            // Dropping the context abandons the ring producer, letting
+            // the writer thread drain and exit before we count.
+            drop(ctx);
+            if let Some(handle) = ring_thread {
+                let _ = handle.join();
+            }
+            let written = writer
+                .lock()
+                .expect("harness writer lock")
+                .take()
+                .map(|writer| {
+                    let samples = writer.len() as u64;
+                    let _ = writer.finalize();
+                    samples
+                })
+                .unwrap_or(0);
+            let _ = std::fs::remove_file(&path);
+            (written, dropped.load(Ordering::Relaxed))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A sink whose backing buffer outlives the writer, so a test can
+    /// inspect the finished wav bytes after `finalize` consumed the
+    /// writer.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Cursor<Vec<u8>>>>);
+
+    impl SharedBuffer {
+        fn bytes(&self) -> Vec<u8> {
+            self.0.lock().unwrap().get_ref().clone()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.0.lock().unwrap().flush()
+        }
+    }
+
+    impl Seek for SharedBuffer {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.0.lock().unwrap().seek(pos)
+        }
+    }
+
+    impl Read for SharedBuffer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().read(buf)
+        }
+    }
+
+    /// A deterministic stand-in for a cpal device: a fixed interleaved
+    /// f32 buffer pushed through the same `write_input_data` path the
+    /// real audio callback uses, bypassing the hardware entirely.
+    struct MockInput {
+        samples: Vec<f32>,
+    }
+
+    impl MockInput {
+        fn from_samples(samples: Vec<f32>) -> Self {
+            MockInput { samples }
+        }
+
+        /// A full-scale sine at `freq_hz`, identical on every channel.
+        fn sine(freq_hz: f32, sample_rate: u32, channels: u16, frames: usize) -> Self {
+            let mut samples = Vec::with_capacity(frames * channels as usize);
+            for frame in 0..frames {
+                let phase = 2.0 * std::f32::consts::PI * freq_hz * frame as f32
+                    / sample_rate as f32;
+                for _ in 0..channels {
+                    samples.push(phase.sin());
+                }
+            }
+            MockInput { samples }
+        }
+
+        /// Runs the samples through the callback into an in-memory wav
+        /// writer and returns the finished file's bytes.
+        fn record_i16(&self, ctx: &CallbackContext, buffer: &SharedBuffer) -> Vec<u8> {
+            write_input_data::<f32, i16>(&self.samples, ctx);
+            let writer = ctx.writer.lock().unwrap().take().unwrap();
+            writer.finalize().unwrap();
+            buffer.bytes()
+        }
+    }
+
+    /// Builds a context around an in-memory mono/i16-style writer with
+    /// the given spec, with everything else at its defaults.
+    fn context(spec: WavSpec, input_channels: u16) -> (CallbackContext, SharedBuffer) {
+        let buffer = SharedBuffer::default();
+        let sink: Box<dyn WavSink> = Box::new(buffer.clone());
+        let writer = WavWriter::new(sink, spec).unwrap();
+        let ctx = CallbackContext {
+            writer: Arc::new(Mutex::new(Some(writer))),
+            dropped: Arc::new(AtomicU64::new(0)),
+            write_errors: Arc::new(AtomicU64::new(0)),
+            samples_written: Arc::new(AtomicU64::new(0)),
+            peak: Arc::new(AtomicU32::new(0)),
+            pretrigger: Arc::new(Mutex::new(VecDeque::new())),
+            gain: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            channel_gains: None,
+            gain_clipped: Arc::new(AtomicBool::new(false)),
+            soft_clip: false,
+            dither: None,
+            session_peak: Arc::new(AtomicU32::new(0)),
+            clipped_samples: Arc::new(AtomicU64::new(0)),
+            total_samples: Arc::new(AtomicU64::new(0)),
+            channels: input_channels,
+            downmix: false,
+            selection: None,
+            split_writers: Arc::new(Mutex::new(Vec::new())),
+            split: false,
+            highpass: None,
+            decimate: None,
+            tone: None,
+            level_tx: None,
+            resample_tx: None,
+            encoder_tx: None,
+            spectrum_tx: None,
+            tcp_tx: None,
+            tcp_dropped: Arc::new(AtomicU64::new(0)),
+            loudness_tx: None,
+            balance_tx: None,
+            timeline_tx: None,
+            buffer_cb: None,
+            ring: None,
+        };
+        (ctx, buffer)
+    }
+
+    fn spec(channels: u16) -> WavSpec {
+        WavSpec {
+            channels,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        }
+    }
+
+    fn read_i16(bytes: Vec<u8>) -> (WavSpec, Vec<i16>) {
+        let reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        let spec = reader.spec();
+        let samples = reader.into_samples().collect::<Result<_, _>>().unwrap();
+        (spec, samples)
+    }
+
+    #[test]
+    fn converts_f32_input_to_i16_wav_bytes() {
+        let input = MockInput::from_samples(vec![0.0, 0.5, -0.5, 1.0]);
+        let (ctx, buffer) = context(spec(1), 1);
+        let (read_spec, samples) = read_i16(input.record_i16(&ctx, &buffer));
+        let expected: Vec<i16> = input.samples.iter().map(|&s| i16::from_sample(s)).collect();
+        assert_eq!(read_spec, spec(1));
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn preserves_channel_count_and_frames() {
+        let input = MockInput::sine(1000.0, 48000, 2, 480);
+        let (ctx, buffer) = context(spec(2), 2);
+        let (read_spec, samples) = read_i16(input.record_i16(&ctx, &buffer));
+        assert_eq!(read_spec.channels, 2);
+        assert_eq!(samples.len(), 480 * 2);
+    }
+
+    #[test]
+    fn downmix_averages_all_channels() {
+        let input = MockInput::from_samples(vec![0.0, 0.5, -0.25, 0.75]);
+        let (mut ctx, buffer) = context(spec(1), 2);
+        ctx.downmix = true;
+        let (read_spec, samples) = read_i16(input.record_i16(&ctx, &buffer));
+        assert_eq!(read_spec.channels, 1);
+        assert_eq!(
+            samples,
+            vec![i16::from_sample(0.25f32), i16::from_sample(0.25f32)]
+        );
+    }
+
+    #[test]
+    fn gain_scales_and_clamps_samples() {
+        let input = MockInput::from_samples(vec![0.25, -0.75]);
+        let (ctx, buffer) = context(spec(1), 1);
+        ctx.gain.store(2.0f32.to_bits(), Ordering::Relaxed);
+        let (_, samples) = read_i16(input.record_i16(&ctx, &buffer));
+        assert_eq!(
+            samples,
+            vec![i16::from_sample(0.5f32), i16::from_sample(-1.0f32)]
+        );
+        assert!(ctx.gain_clipped.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn channel_selection_keeps_only_chosen_channels() {
+        let input = MockInput::from_samples(vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6]);
+        let (mut ctx, buffer) = context(spec(1), 3);
+        ctx.selection = Some(vec![1]);
+        let (_, samples) = read_i16(input.record_i16(&ctx, &buffer));
+        assert_eq!(
+            samples,
+            vec![i16::from_sample(0.2f32), i16::from_sample(0.5f32)]
+        );
+    }
+
+    #[test]
+    fn highpass_removes_dc_offset() {
+        let frames = 48000;
+        let mut input = MockInput::sine(1000.0, 48000, 1, frames);
+        for sample in &mut input.samples {
+            *sample = *sample * 0.5 + 0.4;
+        }
+        let (mut ctx, buffer) = context(spec(1), 1);
+        ctx.highpass = Some(Arc::new(Mutex::new(HighPass::new(100.0, 48000, 1))));
+        let (_, samples) = read_i16(input.record_i16(&ctx, &buffer));
+        // Once the filter has settled, the mean over whole periods of the
+        // remaining signal is near zero despite the +0.4 DC offset in.
+        let tail = &samples[frames / 2..];
+        let mean = tail.iter().map(|&s| f64::from(s)).sum::<f64>() / tail.len() as f64;
+        assert!(mean.abs() < 100.0, "dc offset survived the filter: {}", mean);
+    }
+}
@@ -0,0 +1,159 @@
+//! Sample-rate conversion worker. A sinc or FFT resampler is far too slow
+//! to run inside the cpal audio callback, so the callback hands interleaved
+//! f32 buffers over a bounded channel to a worker thread that resamples
+//! whole chunks and writes them through the shared writer handle. The FFT
+//! resampler processes fixed chunks, which adds roughly one chunk of
+//! latency (1024 frames at the input rate) to the written data; the final
+//! partial chunk is padded with silence when the stream stops.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Error;
+use cpal::Sample;
+use hound::WavSpec;
+use rubato::{FftFixedIn, Resampler};
+
+use crate::recorder::{record_write_failures, WriteHandle};
+
+/// Input frames consumed per resampler pass.
+pub(crate) const CHUNK_FRAMES: usize = 1024;
+
+/// How many callback buffers may queue up for the worker before new ones
+/// are dropped instead of blocking the audio thread.
+pub(crate) const QUEUE_DEPTH: usize = 64;
+
+/// Spawns the resampling worker. It exits once every sender attached to
+/// `rx` is gone, i.e. when the stream is dropped.
+pub(crate) fn spawn_worker(
+    rx: Receiver<Vec<f32>>,
+    writer: WriteHandle,
+    spec: WavSpec,
+    input_rate: u32,
+    dropped: Arc<AtomicU64>,
+    write_errors: Arc<AtomicU64>,
+    samples_written: Arc<AtomicU64>,
+) -> Result<(), Error> {
+    let resampler = FftFixedIn::<f32>::new(
+        input_rate as usize,
+        spec.sample_rate as usize,
+        CHUNK_FRAMES,
+        2,
+        spec.channels as usize,
+    )?;
+    let tallies = Tallies {
+        dropped,
+        write_errors,
+        samples_written,
+    };
+    thread::spawn(move || run_worker(resampler, rx, writer, spec, tallies));
+    Ok(())
+}
+
+/// The shared counters the worker updates as it writes.
+struct Tallies {
+    dropped: Arc<AtomicU64>,
+    write_errors: Arc<AtomicU64>,
+    samples_written: Arc<AtomicU64>,
+}
+
+fn run_worker(
+    mut resampler: FftFixedIn<f32>,
+    rx: Receiver<Vec<f32>>,
+    writer: WriteHandle,
+    spec: WavSpec,
+    tallies: Tallies,
+) {
+    let channels = spec.channels as usize;
+    let chunk_samples = CHUNK_FRAMES * channels;
+    let mut pending: Vec<f32> = Vec::with_capacity(chunk_samples * 2);
+    loop {
+        match rx.recv() {
+            Ok(buffer) => pending.extend(buffer),
+            Err(_) => {
+                // The stream is gone; pad what is left to a full chunk so
+                // the tail of the recording is not lost.
+                if !pending.is_empty() {
+                    pending.resize(chunk_samples, 0.0);
+                    process_chunk(&mut resampler, &pending, channels, &writer, spec, &tallies);
+                }
+                return;
+            }
+        }
+        while pending.len() >= chunk_samples {
+            process_chunk(
+                &mut resampler,
+                &pending[..chunk_samples],
+                channels,
+                &writer,
+                spec,
+                &tallies,
+            );
+            pending.drain(..chunk_samples);
+        }
+    }
+}
+
+/// Resamples one fixed-size interleaved chunk and writes the result in the
+/// file's sample format. Failed writes are recorded as write errors so
+/// the health checks stop the recording, matching the direct write path;
+/// samples lost to a missing writer or a resampler error only count as
+/// dropped. Samples that land count toward the per-file total, so the
+/// minimum-event check and duration reporting see resampled output.
+fn process_chunk(
+    resampler: &mut FftFixedIn<f32>,
+    chunk: &[f32],
+    channels: usize,
+    writer: &WriteHandle,
+    spec: WavSpec,
+    tallies: &Tallies,
+) {
+    let mut planar = vec![Vec::with_capacity(CHUNK_FRAMES); channels];
+    for frame in chunk.chunks_exact(channels) {
+        for (channel, &sample) in frame.iter().enumerate() {
+            planar[channel].push(sample);
+        }
+    }
+    let output = match resampler.process(&planar, None) {
+        Ok(output) => output,
+        Err(err) => {
+            log::error!("resampling error: {}", err);
+            tallies.dropped.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            return;
+        }
+    };
+    let frames = output.first().map(Vec::len).unwrap_or(0);
+    // A poisoned lock means the thread holding the writer panicked; give
+    // up on this chunk rather than taking the worker down with it.
+    let Ok(mut guard) = writer.lock() else {
+        return;
+    };
+    let Some(writer) = guard.as_mut() else {
+        tallies
+            .dropped
+            .fetch_add((frames * channels) as u64, Ordering::Relaxed);
+        return;
+    };
+    let mut landed = 0u64;
+    for frame in 0..frames {
+        for channel_data in &output {
+            let sample = channel_data[frame];
+            let result = match (spec.sample_format, spec.bits_per_sample) {
+                (hound::SampleFormat::Float, _) => writer.write_sample(sample),
+                (hound::SampleFormat::Int, 32) => writer.write_sample(i32::from_sample(sample)),
+                (hound::SampleFormat::Int, 24) => {
+                    writer.write_sample(i32::from_sample(sample) >> 8)
+                }
+                (hound::SampleFormat::Int, 8) => writer.write_sample(i8::from_sample(sample)),
+                (hound::SampleFormat::Int, _) => writer.write_sample(i16::from_sample(sample)),
+            };
+            match result {
+                Ok(()) => landed += 1,
+                Err(_) => record_write_failures(&tallies.dropped, &tallies.write_errors, 1),
+            }
+        }
+    }
+    tallies.samples_written.fetch_add(landed, Ordering::Relaxed);
+}
@@ -0,0 +1,139 @@
+//! PNG spectrograms rendered after a file finalizes, so a reviewer can
+//! eyeball a recording without opening an analysis tool. Rendering runs
+//! on its own thread and reads the finalized wav back from disk — it
+//! never touches the capture path or delays the next segment. The whole
+//! file is folded to mono in memory before the STFT, which is fine for
+//! the bounded segment sizes the recorder produces.
+//!
+//! The image is a Hann-windowed STFT on a linear frequency axis, low
+//! frequencies at the bottom, magnitudes in dB mapped to 8-bit grayscale
+//! between a configurable floor and full scale.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::thread;
+
+use anyhow::{anyhow, Error};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// Rendering parameters, with defaults sized for a quick-look thumbnail.
+#[derive(Clone, Copy, Debug)]
+pub struct SpectrogramConfig {
+    /// Image width in pixels; one STFT column per pixel.
+    pub width: u32,
+    /// Image height in pixels; frequency bins are resampled onto it.
+    pub height: u32,
+    /// STFT frame length in samples.
+    pub fft_size: usize,
+    /// Top of the displayed frequency range; None means Nyquist.
+    pub max_freq_hz: Option<f32>,
+    /// Magnitudes at or below this many dBFS map to black.
+    pub floor_db: f32,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        SpectrogramConfig {
+            width: 1024,
+            height: 512,
+            fft_size: 1024,
+            max_freq_hz: None,
+            floor_db: -90.0,
+        }
+    }
+}
+
+/// Renders the spectrogram for `path` on a background thread. Failures
+/// are logged, never propagated: a missing thumbnail must not fail the
+/// recording that produced it.
+pub(crate) fn spawn_render(path: String, config: SpectrogramConfig) {
+    thread::spawn(move || {
+        if let Err(err) = render(&path, &config) {
+            log::warn!("spectrogram for {} failed: {:#}", path, err);
+        }
+    });
+}
+
+fn render(path: &str, config: &SpectrogramConfig) -> Result<(), Error> {
+    let (mono, sample_rate) = read_mono(path)?;
+    if mono.len() < config.fft_size {
+        return Err(anyhow!("file shorter than one FFT frame"));
+    }
+    let pixels = stft_image(&mono, sample_rate, config);
+    let out = Path::new(path).with_extension("png");
+    let file = BufWriter::new(File::create(&out)?);
+    let mut encoder = png::Encoder::new(file, config.width, config.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    log::info!("SPECTROGRAM: {}", out.display());
+    Ok(())
+}
+
+/// Reads the wav back and folds all channels down to normalized mono.
+fn read_mono(path: &str) -> Result<(Vec<f32>, u32), Error> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<Vec<f32>, hound::Error>>()?,
+        hound::SampleFormat::Int => {
+            let full_scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 / full_scale))
+                .collect::<Result<Vec<f32>, hound::Error>>()?
+        }
+    };
+    let mono = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((mono, spec.sample_rate))
+}
+
+/// Computes the STFT and paints it into a row-major grayscale buffer,
+/// top row at the highest displayed frequency.
+fn stft_image(mono: &[f32], sample_rate: u32, config: &SpectrogramConfig) -> Vec<u8> {
+    let width = config.width as usize;
+    let height = config.height as usize;
+    let fft_size = config.fft_size;
+    let fft = FftPlanner::new().plan_fft_forward(fft_size);
+    let window: Vec<f32> = (0..fft_size)
+        .map(|i| {
+            let phase = 2.0 * std::f32::consts::PI * i as f32 / (fft_size - 1) as f32;
+            0.5 * (1.0 - phase.cos())
+        })
+        .collect();
+    // One column per pixel: the hop spreads the frames evenly over the
+    // file, overlapping when the file is short and skipping when long.
+    let hop = (mono.len().saturating_sub(fft_size) / width.max(1)).max(1);
+    let nyquist = sample_rate as f32 / 2.0;
+    let top_hz = config
+        .max_freq_hz
+        .map_or(nyquist, |hz| hz.clamp(0.0, nyquist));
+    let top_bin = ((top_hz / nyquist) * (fft_size / 2) as f32).max(1.0) as usize;
+    let mut pixels = vec![0u8; width * height];
+    for column in 0..width {
+        let start = (column * hop).min(mono.len() - fft_size);
+        let mut frame: Vec<Complex<f32>> = mono[start..start + fft_size]
+            .iter()
+            .zip(&window)
+            .map(|(&sample, &weight)| Complex::new(sample * weight, 0.0))
+            .collect();
+        fft.process(&mut frame);
+        for row in 0..height {
+            // Row 0 is the top of the image, i.e. the highest frequency.
+            let bin = (top_bin - 1) * (height - 1 - row) / (height - 1).max(1);
+            let magnitude = frame[bin.min(fft_size / 2 - 1)].norm() / (fft_size as f32 / 2.0);
+            let db = 20.0 * magnitude.max(1e-12).log10();
+            let level = ((db - config.floor_db) / -config.floor_db).clamp(0.0, 1.0);
+            pixels[row * width + column] = (level * 255.0) as u8;
+        }
+    }
+    pixels
+}
@@ -0,0 +1,80 @@
+//! Live spectrum tap for confirming a hydrophone hears something. The FFT
+//! never runs in the audio callback: mono-folded buffers are queued to a
+//! worker thread that accumulates them into fixed frames, applies the
+//! window, and hands magnitude bins to the callback. This is a monitoring
+//! aid, not an archival path, so buffers are dropped when the worker falls
+//! behind.
+
+use std::sync::mpsc::Receiver;
+use std::thread;
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// Callback receiving the magnitude bins of each transformed frame.
+pub(crate) type SpectrumCallback = Box<dyn Fn(&[f32]) + Send>;
+
+/// Window applied to each frame before the FFT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowType {
+    Rectangular,
+    Hann,
+    Hamming,
+}
+
+/// How many callback buffers may queue up for the spectrum worker before
+/// new ones are dropped instead of blocking the audio thread.
+pub(crate) const QUEUE_DEPTH: usize = 16;
+
+/// Spawns the spectrum worker. It exits once every sender attached to
+/// `rx` is gone.
+pub(crate) fn spawn_worker(
+    rx: Receiver<Vec<f32>>,
+    fft_size: usize,
+    window: WindowType,
+    callback: SpectrumCallback,
+) {
+    thread::spawn(move || run_worker(rx, fft_size, window, callback));
+}
+
+fn run_worker(
+    rx: Receiver<Vec<f32>>,
+    fft_size: usize,
+    window: WindowType,
+    callback: SpectrumCallback,
+) {
+    let fft = FftPlanner::new().plan_fft_forward(fft_size);
+    let coefficients = window_coefficients(window, fft_size);
+    let mut pending: Vec<f32> = Vec::with_capacity(fft_size * 2);
+    let mut magnitudes = vec![0.0f32; fft_size / 2];
+    while let Ok(buffer) = rx.recv() {
+        pending.extend(buffer);
+        while pending.len() >= fft_size {
+            let mut frame: Vec<Complex<f32>> = pending[..fft_size]
+                .iter()
+                .zip(&coefficients)
+                .map(|(&sample, &weight)| Complex::new(sample * weight, 0.0))
+                .collect();
+            fft.process(&mut frame);
+            for (magnitude, bin) in magnitudes.iter_mut().zip(&frame[..fft_size / 2]) {
+                *magnitude = bin.norm();
+            }
+            callback(&magnitudes);
+            pending.drain(..fft_size);
+        }
+    }
+}
+
+fn window_coefficients(window: WindowType, size: usize) -> Vec<f32> {
+    use std::f32::consts::PI;
+    (0..size)
+        .map(|i| {
+            let phase = 2.0 * PI * i as f32 / (size - 1) as f32;
+            match window {
+                WindowType::Rectangular => 1.0,
+                WindowType::Hann => 0.5 * (1.0 - phase.cos()),
+                WindowType::Hamming => 0.54 - 0.46 * phase.cos(),
+            }
+        })
+        .collect()
+}